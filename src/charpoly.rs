@@ -0,0 +1,297 @@
+/*!
+Characteristic and minimal polynomials.
+
+Both are read off a Krylov basis built in the style of Keller-Gehrig. Taking
+the unit vectors in order, every `e_i` contributes the rows
+`e_i, e_i * A, e_i * A^2, ...` up to the first one which depends on the rows
+before it, counting all rows of the earlier `e_i`. In this basis `A` is
+block triangular with a companion matrix on the diagonal for every `e_i`
+that contributed, so the characteristic polynomial is the product of theirs.
+
+Finding the rows one at a time costs a product with `A` each. Instead the
+rows of every block are doubled at once by multiplying them with
+`A^(2^j)`, and the rows of all blocks are interleaved and put through a
+PLE decomposition, whose rank profile tells which rows to keep. A block
+ends at its first dropped row, later rows of it would depend on the earlier
+ones as well. After about `log2(n)` rounds all blocks have ended, having
+spent a squaring, a product and a PLE decomposition per round, all of
+which are done by `addmul`.
+*/
+
+use crate::gf2mat::*;
+use crate::mul::addmul;
+use crate::ple::ple;
+use crate::poly::GF2Poly;
+use crate::solve::solve_left;
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// The Krylov basis of a square matrix `A`, see the module docs
+pub(crate) struct KrylovBasis<const ALIGN: usize> {
+    /// The rows `e_i * A^k` of all blocks in order, an invertible matrix
+    pub basis: AlignedGF2Mat<ALIGN>,
+    /// The first row and the number of rows of every block
+    pub blocks: Vec<(usize, usize)>,
+    /// The coordinates of the row after the last of every block in `basis`,
+    /// which are zero past the end of the block
+    pub relations: AlignedGF2Mat<ALIGN>,
+}
+
+struct Block {
+    start: usize,
+    len: usize,
+    done: bool,
+}
+
+fn copy_rows<const ALIGN: usize>(
+    dst: &mut AlignedGF2Mat<ALIGN>,
+    dst_row: usize,
+    src: &AlignedGF2Mat<ALIGN>,
+    src_row: usize,
+    n: usize,
+) {
+    for k in 0..n {
+        unsafe { dst.row_slice_mut_unchecked(dst_row + k) }
+            .copy_from_slice(unsafe { src.row_slice_unchecked(src_row + k) });
+    }
+}
+
+/// The bits `pos..pos + n` of a row, packed into words
+fn row_words(row: &[u8], pos: usize, n: usize) -> Vec<u64> {
+    (0..n)
+        .step_by(WORD_BITS)
+        .map(|k| read_bits(row, pos + k, (n - k).min(WORD_BITS)))
+        .collect()
+}
+
+/// Builds the Krylov basis of `a`, which has to be square and non-empty
+pub(crate) fn krylov_basis<const ALIGN: usize>(a: &AlignedGF2Mat<ALIGN>) -> KrylovBasis<ALIGN> {
+    let n = a.nrows();
+    let mut rows = AlignedGF2Mat::<ALIGN>::identity(n);
+    let mut blocks: Vec<Block> = (0..n).map(|i| Block { start: i, len: 1, done: false }).collect();
+    let mut pow = a.clone();
+    let mut tmp = AlignedGF2Mat::<ALIGN>::zero(n, n);
+    loop {
+        let nopen: usize = blocks.iter().filter(|b| !b.done).map(|b| b.len).sum();
+        let mut open = AlignedGF2Mat::<ALIGN>::zero(nopen, n);
+        let mut r = 0;
+        for b in blocks.iter().filter(|b| !b.done) {
+            copy_rows(&mut open, r, &rows, b.start, b.len);
+            r += b.len;
+        }
+        let mut next = AlignedGF2Mat::<ALIGN>::zero(nopen, n);
+        unsafe { addmul(&mut next, &open, &pow) };
+
+        // every open block is followed by its doubled rows
+        let mut stacked = AlignedGF2Mat::<ALIGN>::zero(rows.nrows() + nopen, n);
+        let (mut s, mut r) = (0, 0);
+        let mut segments = Vec::with_capacity(blocks.len());
+        for b in &blocks {
+            copy_rows(&mut stacked, s, &rows, b.start, b.len);
+            let mut len = b.len;
+            if !b.done {
+                copy_rows(&mut stacked, s + len, &next, r, b.len);
+                r += b.len;
+                len *= 2;
+            }
+            segments.push((s, len));
+            s += len;
+        }
+        // the pivot columns of the transpose are the first independent rows
+        let mut kept = vec![false; stacked.nrows()];
+        for p in ple(&mut stacked.transpose()).rank_profile {
+            kept[p] = true;
+        }
+
+        let mut s = 0;
+        for (b, &(start, len)) in blocks.iter_mut().zip(&segments) {
+            let keep = kept[start..start + len].iter().take_while(|&&k| k).count();
+            b.done |= keep < len;
+            b.start = start;
+            b.len = keep;
+            s += keep;
+        }
+        blocks.retain(|b| b.len != 0);
+        rows = AlignedGF2Mat::<ALIGN>::zero(s, n);
+        let mut r = 0;
+        for b in &mut blocks {
+            copy_rows(&mut rows, r, &stacked, b.start, b.len);
+            b.start = r;
+            r += b.len;
+        }
+        if blocks.iter().all(|b| b.done) {
+            break;
+        }
+        tmp.clear();
+        unsafe { addmul(&mut tmp, &pow, &pow) };
+        std::mem::swap(&mut pow, &mut tmp);
+    }
+    debug_assert_eq!(rows.nrows(), n);
+
+    let mut last = AlignedGF2Mat::<ALIGN>::zero(blocks.len(), n);
+    for (i, b) in blocks.iter().enumerate() {
+        copy_rows(&mut last, i, &rows, b.start + b.len - 1, 1);
+    }
+    let mut after = AlignedGF2Mat::<ALIGN>::zero(blocks.len(), n);
+    unsafe { addmul(&mut after, &last, a) };
+    let relations = solve_left(&rows, &after).expect("the Krylov basis is invertible");
+    KrylovBasis {
+        basis: rows,
+        blocks: blocks.iter().map(|b| (b.start, b.len)).collect(),
+        relations,
+    }
+}
+
+impl<const ALIGN: usize> KrylovBasis<ALIGN> {
+    /// The characteristic polynomial of the companion matrix of block `b`
+    pub fn block_poly(&self, b: usize) -> GF2Poly {
+        let (start, len) = self.blocks[b];
+        let row = unsafe { self.relations.row_slice_unchecked(b) };
+        let mut words = row_words(row, start, len);
+        words.resize(len / WORD_BITS + 1, 0);
+        words[len / WORD_BITS] |= 1 << (len % WORD_BITS);
+        GF2Poly::from_words(words)
+    }
+}
+
+/// Applies `A` to coordinates in the Krylov basis, which shifts them within
+/// every block and adds the relation of every block whose last coordinate
+/// is set
+pub(crate) struct Coordinates {
+    /// The first coordinate of every block, and `n`
+    starts: Vec<u64>,
+    /// The last coordinate of every block
+    lasts: Vec<u64>,
+    /// The block every coordinate belongs to
+    block_of: Vec<usize>,
+    relations: Vec<Vec<u64>>,
+}
+
+pub(crate) fn get_bit(words: &[u64], i: usize) -> bool {
+    (words[i / WORD_BITS] >> (i % WORD_BITS)) & 1 == 1
+}
+
+pub(crate) fn set_bit(words: &mut [u64], i: usize) {
+    words[i / WORD_BITS] |= 1 << (i % WORD_BITS);
+}
+
+pub(crate) fn xor_words(dst: &mut [u64], src: &[u64]) {
+    dst.iter_mut().zip(src).for_each(|(d, s)| *d ^= s);
+}
+
+impl Coordinates {
+    pub fn new<const ALIGN: usize>(krylov: &KrylovBasis<ALIGN>) -> Self {
+        let n = krylov.relations.ncols();
+        let mut starts = vec![0; n / WORD_BITS + 1];
+        let mut lasts = vec![0; n.div_ceil(WORD_BITS)];
+        let mut block_of = vec![0; n];
+        for (b, &(start, len)) in krylov.blocks.iter().enumerate() {
+            set_bit(&mut starts, start);
+            set_bit(&mut lasts, start + len - 1);
+            block_of[start..start + len].fill(b);
+        }
+        set_bit(&mut starts, n);
+        let relations = (0..krylov.blocks.len())
+            .map(|b| row_words(unsafe { krylov.relations.row_slice_unchecked(b) }, 0, n))
+            .collect();
+        Self { starts, lasts, block_of, relations }
+    }
+
+    /// `z * A` for `z` within the first `z.len()` words, the coordinates
+    /// past which have to belong to blocks not reached by `z`
+    pub fn step(&self, z: &[u64]) -> Vec<u64> {
+        let mut next = vec![0; z.len()];
+        let mut carry = 0;
+        for (d, (&s, &m)) in next.iter_mut().zip(z.iter().zip(&self.starts)) {
+            *d = ((s << 1) | carry) & !m;
+            carry = s >> (WORD_BITS - 1);
+        }
+        for (w, (&s, &m)) in z.iter().zip(&self.lasts).enumerate() {
+            let mut bits = s & m;
+            while bits != 0 {
+                let l = w * WORD_BITS + bits.trailing_zeros() as usize;
+                xor_words(&mut next, &self.relations[self.block_of[l]]);
+                bits &= bits - 1;
+            }
+        }
+        next
+    }
+
+    /// `y * h(A)` by Horner's scheme
+    pub fn apply_poly(&self, y: &[u64], h: &GF2Poly) -> Vec<u64> {
+        let mut acc = vec![0; y.len()];
+        for k in (0..=h.degree().unwrap_or(0)).rev() {
+            acc = self.step(&acc);
+            if h.coeff(k) {
+                xor_words(&mut acc, y);
+            }
+        }
+        acc
+    }
+}
+
+/// The characteristic polynomial of a square matrix
+pub(crate) fn charpoly<const ALIGN: usize, S: GF2MatLike<ALIGN>>(mat: &S) -> GF2Poly {
+    if mat.nrows() == 0 {
+        return GF2Poly::one();
+    }
+    let krylov = krylov_basis(&mat.to_mat());
+    (0..krylov.blocks.len()).fold(GF2Poly::one(), |acc, b| &acc * &krylov.block_poly(b))
+}
+
+/// The minimal polynomials of the first rows `x_b` of the blocks.
+///
+/// Modulo the earlier blocks `x_b` is annihilated by its block polynomial
+/// `p_b`, and the vector `y = x_b * p_b(A)` which remains has its minimal
+/// polynomial found the same way: the coordinates of `y` in its last block
+/// `c` are the coefficients of some `q` with `y = x_c * q(A)` modulo the
+/// blocks before `c`, where it is annihilated by `h = p_c / gcd(p_c, q)`.
+/// Then `y * h(A)` lies before `c` and it goes on from there.
+pub(crate) fn annihilators<const ALIGN: usize>(
+    krylov: &KrylovBasis<ALIGN>,
+    coords: &Coordinates,
+    polys: &[GF2Poly],
+) -> Vec<GF2Poly> {
+    let mut anns = Vec::with_capacity(krylov.blocks.len());
+    for (b, &(start, _)) in krylov.blocks.iter().enumerate() {
+        let mut ann = polys[b].clone();
+        let mut y = coords.relations[b][..start.div_ceil(WORD_BITS)].to_vec();
+        if let Some(w) = y.last_mut() {
+            *w &= low_mask((start - 1) % WORD_BITS + 1);
+        }
+        for c in (0..b).rev() {
+            let (start, len) = krylov.blocks[c];
+            let q: Vec<u64> = (0..len)
+                .step_by(WORD_BITS)
+                .map(|k| {
+                    let n = (len - k).min(WORD_BITS);
+                    (0..n).fold(0, |w, i| w | (get_bit(&y, start + k + i) as u64) << i)
+                })
+                .collect();
+            let q = GF2Poly::from_words(q);
+            if q.is_zero() {
+                continue;
+            }
+            let h = polys[c].div_rem(&polys[c].gcd(&q)).0;
+            y = coords.apply_poly(&y, &h);
+            ann = &ann * &h;
+        }
+        anns.push(ann);
+    }
+    anns
+}
+
+/// The minimal polynomial of a square matrix. The rows of all blocks span
+/// the whole space, so it is the least common multiple of the minimal
+/// polynomials of the first rows of the blocks.
+pub(crate) fn minpoly<const ALIGN: usize, S: GF2MatLike<ALIGN>>(mat: &S) -> GF2Poly {
+    if mat.nrows() == 0 {
+        return GF2Poly::one();
+    }
+    let krylov = krylov_basis(&mat.to_mat());
+    let coords = Coordinates::new(&krylov);
+    let polys: Vec<GF2Poly> = (0..krylov.blocks.len()).map(|b| krylov.block_poly(b)).collect();
+    annihilators(&krylov, &coords, &polys)
+        .iter()
+        .fold(GF2Poly::one(), |acc, ann| acc.lcm(ann))
+}
@@ -1,6 +1,6 @@
 use crate::decomp_macro::decomp_fn;
 
-decomp_fn!(addmul_decomp2x2, 2, |a_blks, b_blks, c_blks, tmp_mk, tmp_mn, tmp_kn, algos| {
+decomp_fn!(addmul_decomp2x2, addmul_decomp2x2_par, 2, |a_blks, b_blks, c_blks, tmp_mk, tmp_mn, tmp_kn, algos| {
     tmp_mk.set_to_sum_unchecked(&a_blks[1][0], &a_blks[1][1]);
     tmp_kn.set_to_sum_unchecked(&b_blks[1][0], &b_blks[1][1]);
     addmul_recurse(&mut tmp_mn, &tmp_mk, &tmp_kn, &algos);
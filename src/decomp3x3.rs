@@ -1,6 +1,6 @@
 use crate::decomp_macro::decomp_fn;
 
-decomp_fn!(addmul_decomp3x3, 3, |a_blks, b_blks, c_blks, tmp_mk, tmp_mn, tmp_kn, algos| {
+decomp_fn!(addmul_decomp3x3, addmul_decomp3x3_par, 3, |a_blks, b_blks, c_blks, tmp_mk, tmp_mn, tmp_kn, algos| {
     tmp_mk.set_to_sum_unchecked(&a_blks[0][0], &a_blks[2][0]);
     tmp_kn.set_to_sum_unchecked(&b_blks[0][0], &b_blks[1][0]);
     tmp_kn.add_unchecked(&b_blks[2][0]);
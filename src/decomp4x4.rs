@@ -1,6 +1,6 @@
 use crate::decomp_macro::decomp_fn;
 
-decomp_fn!(addmul_decomp4x4, 4, |a_blks, b_blks, c_blks, tmp_mk, tmp_mn, tmp_kn, algos| {
+decomp_fn!(addmul_decomp4x4, addmul_decomp4x4_par, 4, |a_blks, b_blks, c_blks, tmp_mk, tmp_mn, tmp_kn, algos| {
     tmp_mk.add_unchecked(&a_blks[0][2]);
     tmp_kn.add_unchecked(&b_blks[2][0]);
     addmul_recurse(&mut tmp_mn, &tmp_mk, &tmp_kn, &algos);
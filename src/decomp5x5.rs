@@ -1,6 +1,6 @@
 use crate::decomp_macro::decomp_fn;
 
-decomp_fn!(addmul_decomp5x5, 5, |a_blks, b_blks, c_blks, tmp_mk, tmp_mn, tmp_kn, algos| {
+decomp_fn!(addmul_decomp5x5, addmul_decomp5x5_par, 5, |a_blks, b_blks, c_blks, tmp_mk, tmp_mn, tmp_kn, algos| {
     tmp_mk.add_unchecked(&a_blks[1][1]);
     tmp_kn.add_unchecked(&b_blks[1][3]);
     addmul_recurse(&mut tmp_mn, &tmp_mk, &tmp_kn, &algos);
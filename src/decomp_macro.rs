@@ -1,7 +1,8 @@
 macro_rules! decomp_fn {
-    ($func_name: ident, $dim:expr, |$a_blks:ident, $b_blks:ident, $c_blks:ident, $tmp_mk:ident, $tmp_mn:ident, $tmp_kn:ident, $algos:ident| $body: block) => {
+    ($func_name: ident, $par_name: ident, $dim:expr, |$a_blks:ident, $b_blks:ident, $c_blks:ident, $tmp_mk:ident, $tmp_mn:ident, $tmp_kn:ident, $algos:ident| $body: block) => {
         use crate::gf2mat::{AlignedGF2Mat, GF2MatLike, GF2MatLikeMut};
         use crate::mul::{addmul_recurse, AddMulAlgo};
+        use crate::parallel::{run_scheme, Recorder, Scheme, Sym};
         const U8SZ: usize = u8::BITS as usize;
         pub unsafe fn $func_name<const ALIGN: usize, const WINDOW_ALIGN: usize, T, S1, S2>(
             tgt: &mut T,
@@ -46,6 +47,63 @@ macro_rules! decomp_fn {
                 { $body }
             }
         }
+
+        /// Same as the sequential version, but the sub-products run on up
+        /// to `threads` threads. The scheme is recorded from the body once,
+        /// by running it on stand-ins for the blocks and temporaries.
+        pub unsafe fn $par_name<const ALIGN: usize, const WINDOW_ALIGN: usize, T, S1, S2>(
+            tgt: &mut T,
+            lhs: &S1,
+            rhs: &S2,
+            algos: &[AddMulAlgo],
+            threads: usize,
+        ) where
+            T: GF2MatLikeMut<ALIGN>,
+            S1: GF2MatLike<ALIGN>,
+            S2: GF2MatLike<ALIGN>,
+        {
+            static SCHEME: std::sync::OnceLock<Scheme> = std::sync::OnceLock::new();
+            let scheme = SCHEME.get_or_init(|| {
+                use crate::parallel::record_addmul as addmul_recurse;
+                let recorder = Recorder::default();
+                let $algos = &recorder;
+                let $a_blks = Sym::division::<$dim>();
+                let $b_blks = Sym::division::<$dim>();
+                let mut $c_blks = <[[Sym; $dim]; $dim]>::default();
+                let mut $tmp_mk = Sym::default();
+                let mut $tmp_mn = Sym::default();
+                let mut $tmp_kn = Sym::default();
+                unsafe { $body }
+                Scheme::from_recording(recorder, &$c_blks)
+            });
+
+            assert_eq!(ALIGN % WINDOW_ALIGN, 0);
+            debug_assert_eq!(tgt.nrows(), lhs.nrows());
+            debug_assert_eq!(tgt.nbyte_cols(), rhs.nbyte_cols());
+            debug_assert!(lhs.max_ncols() >= rhs.nrows());
+
+            let (m, k, n) = (lhs.nrows(), rhs.nrows(), rhs.max_ncols());
+            debug_assert_eq!(k % ($dim * WINDOW_ALIGN * U8SZ), 0);
+            debug_assert_eq!(n % ($dim * WINDOW_ALIGN * U8SZ), 0);
+            let (mm, kk, nn) = (m / $dim, k / $dim, n / $dim);
+            let bk = kk / (U8SZ * WINDOW_ALIGN);
+            let bn = nn / (U8SZ * WINDOW_ALIGN);
+
+            unsafe {
+                let a_blks = lhs.division_unchecked::<WINDOW_ALIGN, $dim, $dim>(mm, bk);
+                let b_blks = rhs.division_unchecked::<WINDOW_ALIGN, $dim, $dim>(kk, bn);
+                let c_blks = tgt.division_mut_unchecked::<WINDOW_ALIGN, $dim, $dim>(mm, bn);
+                run_scheme(
+                    scheme,
+                    a_blks.as_flattened(),
+                    b_blks.as_flattened(),
+                    c_blks.into_iter().flatten().collect(),
+                    (mm, kk, nn),
+                    algos,
+                    threads,
+                );
+            }
+        }
     }
 }
 pub(crate) use decomp_fn;
\ No newline at end of file
@@ -0,0 +1,112 @@
+/*!
+Gaussian elimination with the Method of the Four Russians (M4RI).
+
+The columns are processed in strips of up to `k` columns. Within a strip we
+first look for pivots the slow way, one row operation at a time, keeping the
+pivot rows reduced against each other on the strip. The `2^k` combinations of
+these pivot rows are then tabulated with `tabulate_m4rm`, exactly like in
+`addmul_m4rm`, so clearing the strip from any other row is a single lookup
+followed by a single row addition.
+
+Like in m4ri a strip ends at the first column without a pivot, which keeps the
+pivots of a strip in consecutive columns and lets us index the table by the
+strip bits directly.
+*/
+
+use crate::gf2mat::*;
+use crate::m4rm::{tabulate_m4rm, GRAY_TBL, MAX_TBL_SZ};
+
+/// Strip width for a matrix with `nrows` rows, about `0.75 * log2(nrows)`
+/// which balances building the table against using it.
+pub(crate) fn strip_width(nrows: usize) -> usize {
+    let log = (usize::BITS - nrows.leading_zeros()) as usize;
+    (3 * log / 4).clamp(1, MAX_TBL_SZ)
+}
+
+/// Finds up to `k` pivots in columns `c..c + k` among the rows from `r` on,
+/// moves them to rows `r..` and reduces the strip of the pivot rows to the
+/// identity. Stops at the first column without a pivot and returns the
+/// number of pivots found.
+unsafe fn gauss_strip<const ALIGN: usize, T>(mat: &mut T, r: usize, c: usize, k: usize) -> usize
+where
+    T: GF2MatLikeMut<ALIGN> + ?Sized,
+{
+    for j in 0..k {
+        let mut found = false;
+        for i in r + j..mat.nrows() {
+            // clear the columns of the pivots we already have
+            for t in 0..j {
+                if unsafe { mat.get_unchecked(i, c + t) } {
+                    unsafe { add_row(mat, r + t, i) };
+                }
+            }
+            if unsafe { mat.get_unchecked(i, c + j) } {
+                unsafe { mat.swap_rows_unchecked(i, r + j) };
+                found = true;
+                break;
+            }
+        }
+        if !found {
+            return j;
+        }
+        for t in 0..j {
+            if unsafe { mat.get_unchecked(r + t, c + j) } {
+                unsafe { add_row(mat, r + j, r + t) };
+            }
+        }
+    }
+    k
+}
+
+unsafe fn add_row<const ALIGN: usize, T>(mat: &mut T, src: usize, dst: usize)
+where
+    T: GF2MatLikeMut<ALIGN> + ?Sized,
+{
+    let (dst, src) = unsafe { mat.distinct_row_slices_mut_immut(dst, src) };
+    unsafe { xor_aligned_slices::<ALIGN>(dst, src) };
+}
+
+/// Brings `mat` into row echelon form in place and returns its rank, the
+/// pivot rows end up as the first `rank` rows. With `full` the form is
+/// reduced, i.e. the pivot columns are cleared above the pivots as well.
+pub fn echelonize<const ALIGN: usize, T>(mat: &mut T, full: bool) -> usize
+where
+    T: GF2MatLikeMut<ALIGN> + ?Sized,
+{
+    let (nrows, ncols) = (mat.nrows(), mat.ncols());
+    if nrows == 0 || ncols == 0 {
+        return 0;
+    }
+    let chunk_bits = ALIGN * u8::BITS as usize;
+    let k = strip_width(nrows);
+    let mut tbl = AlignedGF2Mat::<ALIGN>::zero(1 << k, mat.max_ncols());
+
+    let (mut r, mut c) = (0, 0);
+    while r < nrows && c < ncols {
+        // the chunks left of the strip are already done, so only work on
+        // the window from the one holding column c onwards
+        let chunk = c / chunk_bits;
+        let nchunks = mat.nchunk_cols() - chunk;
+        let mut rest = unsafe { mat.get_window_mut_unchecked::<ALIGN>(0, chunk, nrows, nchunks) };
+        let mut tbl_rest = unsafe { tbl.get_window_mut_unchecked::<ALIGN>(0, chunk, 1 << k, nchunks) };
+        let lc = c - chunk * chunk_bits;
+
+        let kbar = unsafe { gauss_strip(&mut rest, r, lc, k.min(ncols - c)) };
+        if kbar == 0 {
+            c += 1;
+            continue;
+        }
+        unsafe { tabulate_m4rm(&mut tbl_rest, &rest, r, kbar) };
+        let above = if full { 0..r } else { 0..0 };
+        for i in above.chain(r + kbar..nrows) {
+            let num = read_bits(unsafe { rest.row_slice_unchecked(i) }, lc, kbar);
+            if num != 0 {
+                let tbl_row = GRAY_TBL[num as usize] as usize;
+                unsafe { rest.add_row_from_mat(&tbl_rest, tbl_row, i) };
+            }
+        }
+        r += kbar;
+        c += kbar;
+    }
+    r
+}
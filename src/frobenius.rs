@@ -0,0 +1,219 @@
+/*!
+The Frobenius normal form, also known as the rational canonical form.
+
+Every square matrix is similar to a block diagonal matrix of companion
+matrices of its invariant factors `f_1 | f_2 | ... | f_k`, the last of which
+is the minimal polynomial. The factors are split off from the largest one
+down:
+
+- A vector `v` whose minimal polynomial is the minimal polynomial `f` of `A`
+  is put together from the first rows of the blocks of the Krylov basis,
+  see `charpoly`. If `x` and `y` are annihilated by `a` and `b`, splitting
+  `lcm(a, b)` into coprime `u | a` and `w | b` makes
+  `x * (a / u)(A) + y * (b / w)(A)` annihilated by exactly `u * w`.
+- The rows `v * A^k` for `k < deg f` span an invariant subspace `K`. With
+  `u` chosen such that `v * A^k * u` is zero except for `k = deg f - 1`, the
+  vectors `w` with `w * A^k * u = 0` for all `k < deg f` form an invariant
+  complement of `K`, as the matrix of the `v * A^(i + j) * u` is
+  triangular with ones on its antidiagonal.
+- What remains is the same problem for `A` restricted to the complement.
+
+All products go through `addmul`, and every step is a Krylov basis, a few
+products and solves, so the cost is a small multiple of `charpoly` per
+invariant factor.
+*/
+
+use crate::charpoly::{annihilators, krylov_basis, set_bit, xor_words, Coordinates};
+use crate::gf2mat::*;
+use crate::mul::addmul;
+use crate::poly::GF2Poly;
+use crate::solve::{solve, solve_left};
+
+/// The Frobenius normal form `F` of a square matrix `A`, with an invertible
+/// `T` such that `T * A = F * T`
+#[derive(Debug, Clone)]
+pub struct FrobeniusForm<const ALIGN: usize> {
+    /// The invariant factors, every one dividing the next, whose product is
+    /// the characteristic polynomial and the last of which is the minimal
+    /// polynomial
+    pub invariant_factors: Vec<GF2Poly>,
+    /// Block diagonal with a companion matrix for every invariant factor,
+    /// with ones above the diagonal and the coefficients in its last row
+    pub form: AlignedGF2Mat<ALIGN>,
+    /// `T`, the rows of every block being `v, v * A, v * A^2, ...` for some `v`
+    pub transform: AlignedGF2Mat<ALIGN>,
+}
+
+/// Splits `lcm(a, b)` into coprime `u | a` and `w | b`, without factoring
+fn coprime_split(a: &GF2Poly, b: &GF2Poly) -> (GF2Poly, GF2Poly) {
+    // the primes with a higher power in `a` than in `b` are those of `c`,
+    // and `u` is everything of `a` at these
+    let c = a.div_rem(&a.gcd(b)).0;
+    let mut rest = a.clone();
+    let mut d = rest.gcd(&c);
+    while !d.is_one() {
+        rest = rest.div_rem(&d).0;
+        d = rest.gcd(&d);
+    }
+    let u = a.div_rem(&rest).0;
+    let w = a.lcm(b).div_rem(&u).0;
+    (u, w)
+}
+
+/// The rows `v, v * A, ..., v * A^(d - 1)` for a single row `v`, doubling
+/// the number of rows with every power of two of `A`
+fn krylov_rows<const ALIGN: usize>(
+    v: &AlignedGF2Mat<ALIGN>,
+    a: &AlignedGF2Mat<ALIGN>,
+    d: usize,
+) -> AlignedGF2Mat<ALIGN> {
+    let m = a.nrows();
+    let mut rows = AlignedGF2Mat::<ALIGN>::zero(d, m);
+    unsafe { rows.row_slice_mut_unchecked(0) }.copy_from_slice(unsafe { v.row_slice_unchecked(0) });
+    let mut pow = a.clone();
+    let mut have = 1;
+    while have < d {
+        let k = have.min(d - have);
+        let mut next = AlignedGF2Mat::<ALIGN>::zero(k, m);
+        unsafe { addmul(&mut next, &rows.view(..k, ..), &pow) };
+        for i in 0..k {
+            unsafe { rows.row_slice_mut_unchecked(have + i) }
+                .copy_from_slice(unsafe { next.row_slice_unchecked(i) });
+        }
+        have += k;
+        if have < d {
+            pow = &pow * &pow;
+        }
+    }
+    rows
+}
+
+/// `lhs * rhs` for non-empty matrices
+fn product<const ALIGN: usize>(lhs: &AlignedGF2Mat<ALIGN>, rhs: &AlignedGF2Mat<ALIGN>) -> AlignedGF2Mat<ALIGN> {
+    let mut prod = AlignedGF2Mat::<ALIGN>::zero(lhs.nrows(), rhs.ncols());
+    unsafe { addmul(&mut prod, lhs, rhs) };
+    prod
+}
+
+/// The invariant factors from the largest down, and if asked for the rows
+/// of `T` belonging to each of them
+fn split_factors<const ALIGN: usize>(
+    a: &AlignedGF2Mat<ALIGN>,
+    with_transform: bool,
+) -> (Vec<GF2Poly>, Vec<AlignedGF2Mat<ALIGN>>) {
+    let mut factors = Vec::new();
+    let mut blocks = Vec::new();
+    let mut cur = a.clone();
+    // the basis of the subspace `cur` acts on, `None` while it is everything
+    let mut embed: Option<AlignedGF2Mat<ALIGN>> = None;
+    while cur.nrows() != 0 {
+        let m = cur.nrows();
+        let krylov = krylov_basis(&cur);
+        let coords = Coordinates::new(&krylov);
+        let polys: Vec<GF2Poly> = (0..krylov.blocks.len()).map(|b| krylov.block_poly(b)).collect();
+        let anns = annihilators(&krylov, &coords, &polys);
+
+        // the coordinates of a vector annihilated by exactly the minimal polynomial
+        let mut z = vec![0; m.div_ceil(u64::BITS as usize)];
+        let mut f = GF2Poly::one();
+        for (&(start, _), ann) in krylov.blocks.iter().zip(&anns) {
+            if f.div_rem(ann).1.is_zero() {
+                continue;
+            }
+            let (u, w) = coprime_split(&f, ann);
+            let mut x = vec![0; z.len()];
+            set_bit(&mut x, start);
+            let mut next = coords.apply_poly(&x, &ann.div_rem(&w).0);
+            if !u.is_one() {
+                xor_words(&mut next, &coords.apply_poly(&z, &f.div_rem(&u).0));
+            }
+            z = next;
+            f = &u * &w;
+        }
+        let d = f.degree().unwrap();
+
+        // a scalar matrix, every row is a block of its own
+        if d == 1 {
+            factors.extend(std::iter::repeat_n(f, m));
+            if with_transform {
+                blocks.push(embed.unwrap_or_else(|| AlignedGF2Mat::identity(m)));
+            }
+            break;
+        }
+
+        let mut kc = vec![z];
+        for _ in 1..d {
+            kc.push(coords.step(kc.last().unwrap()));
+        }
+        let k = product(&AlignedGF2Mat::from_words(m, &kc), &krylov.basis);
+        if with_transform {
+            blocks.push(match &embed {
+                Some(embed) => product(&k, embed),
+                None => k.clone(),
+            });
+        }
+        factors.push(f);
+        if d == m {
+            break;
+        }
+
+        let mut e = AlignedGF2Mat::<ALIGN>::zero(d, 1);
+        e.set(d - 1, 0, true);
+        let u = solve(&k, &e).expect("the Krylov rows are independent");
+        let complement = krylov_rows(&u.transpose(), &cur.transpose(), d).right_kernel();
+        cur = solve_left(&complement, &product(&complement, &cur)).expect("the complement is invariant");
+        if with_transform {
+            embed = Some(match &embed {
+                Some(embed) => product(&complement, embed),
+                None => complement,
+            });
+        }
+    }
+    (factors, blocks)
+}
+
+/// The Frobenius normal form of a square matrix
+pub(crate) fn frobenius_form<const ALIGN: usize, S: GF2MatLike<ALIGN>>(mat: &S) -> FrobeniusForm<ALIGN> {
+    let n = mat.nrows();
+    let (mut factors, mut blocks) = split_factors(&mat.to_mat(), true);
+    factors.reverse();
+    blocks.reverse();
+
+    let mut form = AlignedGF2Mat::<ALIGN>::zero(n, n);
+    let mut off = 0;
+    for f in &factors {
+        let d = f.degree().unwrap();
+        for k in 0..d - 1 {
+            form.set(off + k, off + k + 1, true);
+        }
+        for j in 0..d {
+            form.set(off + d - 1, off + j, f.coeff(j));
+        }
+        off += d;
+    }
+    let mut transform = AlignedGF2Mat::<ALIGN>::zero(n, n);
+    let mut row = 0;
+    for block in &blocks {
+        for i in 0..block.nrows() {
+            unsafe { transform.row_slice_mut_unchecked(row + i) }
+                .copy_from_slice(unsafe { block.row_slice_unchecked(i) });
+        }
+        row += block.nrows();
+    }
+    FrobeniusForm { invariant_factors: factors, form, transform }
+}
+
+/// Whether `a` and `b` are similar, that is `b = T * a * T^-1` for some
+/// invertible `T`, which is the case when they have the same invariant
+/// factors. Matrices which are not square are never similar.
+pub fn is_similar<const ALIGN: usize, S1, S2>(a: &S1, b: &S2) -> bool
+where
+    S1: GF2MatLike<ALIGN>,
+    S2: GF2MatLike<ALIGN>,
+{
+    let n = a.nrows();
+    if a.ncols() != n || b.nrows() != n || b.ncols() != n {
+        return false;
+    }
+    split_factors(&a.to_mat(), false).0 == split_factors(&b.to_mat(), false).0
+}
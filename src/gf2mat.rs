@@ -12,14 +12,21 @@ feature, so we will have to do without for now.
 
 use std::fmt::Debug;
 use std::fmt::Formatter;
+use std::hash::{Hash, Hasher};
 use std::marker::PhantomData;
+use std::ops::RangeBounds;
 
 use aligned_vec::AVec;
 
-use m4ri_rust::friendly::BinMatrix;
-use rand::{rngs::ThreadRng, Rng};
+use rand::Rng;
 use thiserror::Error;
 
+use crate::frobenius::FrobeniusForm;
+use crate::ple::{has_full_col_rank, ple};
+use crate::poly::GF2Poly;
+use crate::transpose::{transpose_into, transpose_square_in_place};
+use crate::view::{GF2MatView, GF2MatViewMut};
+
 #[derive(Error, Debug)]
 pub enum MatMulError {
     #[error("Cannot multiply matrices of dimensions {0}x{1} and {2}x{3}")]
@@ -36,14 +43,135 @@ pub enum MatAddError {
     DimMismatch(usize, usize, usize, usize),
 }
 
+#[derive(Error, Debug)]
+pub enum MatInvError {
+    #[error("Cannot invert non-square matrix of dimensions {0}x{1}")]
+    NotSquare(usize, usize),
+    #[error("Cannot invert singular matrix of size {0}x{0}")]
+    Singular(usize),
+}
+
+#[derive(Error, Debug)]
+pub enum TrsmError {
+    #[error("Triangular matrix of dimensions {0}x{1} is not square")]
+    NotSquare(usize, usize),
+    #[error("Cannot solve with a {0}x{0} triangular matrix and a {1}x{2} right hand side")]
+    DimMismatch(usize, usize, usize),
+    #[error("Triangular matrix is singular, its diagonal is zero in row {0}")]
+    Singular(usize),
+}
+
+#[derive(Error, Debug)]
+pub enum SolveError {
+    #[error("Cannot solve a system with a {0}x{1} matrix and a {2}x{3} right hand side")]
+    DimMismatch(usize, usize, usize, usize),
+    #[error("The system has no solution")]
+    Inconsistent,
+}
+
+#[derive(Error, Debug)]
+pub enum ProfileError {
+    #[error("Cannot read or write tuning profile: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Malformed tuning profile: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Window alignment {0} is not one of {1:?}")]
+    WindowAlign(usize, [usize; 3]),
+    #[error("Strassen cutoff {0} is below twice the {1} bits of a window chunk")]
+    Cutoff(usize, usize),
+    #[error("Decomposition order {0:?} repeats a decomposition, has M4RM or one not compiled in")]
+    DecompOrder(Vec<crate::mul::AddMulAlgo>),
+}
+
 #[derive(Error, Debug)]
 pub enum MatAccessError {
     #[error("Bit position {0}x{1} is out of bounds for matrix of size {2}x{3}")]
     OutOfBounds(usize, usize, usize, usize),
+    #[error("Row {0} is out of bounds for matrix with {1} rows")]
+    RowOutOfBounds(usize, usize),
+    #[error("Column {0} is out of bounds for matrix with {1} columns")]
+    ColOutOfBounds(usize, usize),
+    #[error("Range {0}..{1} is out of bounds for dimension of size {2}")]
+    RangeOutOfBounds(usize, usize, usize),
 }
 
 // TODO: find automatically
 const CACHELINE_SZ: usize = 0x80;
+const U8SZ: usize = u8::BITS as usize;
+
+/// The logical width of a window starting `col` bits into a matrix with
+/// `parent_ncols` columns, windows reaching into the padding are cut off.
+fn clipped_ncols(parent_ncols: usize, col: usize, width: usize) -> usize {
+    parent_ncols.saturating_sub(col).min(width)
+}
+
+/// Reads up to 8 bytes as a little-endian word, so that bit `j` of the
+/// word is column `j` of the bytes.
+pub(crate) fn load_word(bytes: &[u8]) -> u64 {
+    let mut buf = [0u8; 8];
+    buf[..bytes.len()].copy_from_slice(bytes);
+    u64::from_le_bytes(buf)
+}
+
+/// Inverse of `load_word`, writes the low `bytes.len()` bytes of `word`
+pub(crate) fn store_word(bytes: &mut [u8], word: u64) {
+    let len = bytes.len();
+    bytes.copy_from_slice(&word.to_le_bytes()[..len]);
+}
+
+/// Mask of the low `n` bits, `n` may be anything up to 64
+pub(crate) fn low_mask(n: usize) -> u64 {
+    u64::MAX.unbounded_shr(u64::BITS - n as u32)
+}
+
+/// Reads `n <= 64` bits starting at bit `pos` of a row
+pub(crate) fn read_bits(bytes: &[u8], pos: usize, n: usize) -> u64 {
+    debug_assert!(n <= u64::BITS as usize);
+    debug_assert!(pos + n <= bytes.len() * U8SZ);
+    let start = pos / U8SZ;
+    let shift = pos % U8SZ;
+    let end = (pos + n).div_ceil(U8SZ);
+    let mut word = load_word(&bytes[start..end.min(start + 8)]) >> shift;
+    if end > start + 8 {
+        word |= (bytes[start + 8] as u64) << (u64::BITS as usize - shift);
+    }
+    word & low_mask(n)
+}
+
+/// Zeroes the first `n` bits of a row
+pub(crate) fn clear_prefix(row: &mut [u8], n: usize) {
+    row[..n / U8SZ].fill(0);
+    if !n.is_multiple_of(U8SZ) {
+        row[n / U8SZ] &= !((1u8 << (n % U8SZ)) - 1);
+    }
+}
+
+/// Zeroes all bits of a row from bit `n` on
+pub(crate) fn clear_suffix(row: &mut [u8], n: usize) {
+    row[n.div_ceil(U8SZ)..].fill(0);
+    if !n.is_multiple_of(U8SZ) {
+        row[n / U8SZ] &= (1u8 << (n % U8SZ)) - 1;
+    }
+}
+
+/// Overwrites `n <= 64` bits starting at bit `pos` of a row with the low bits of `val`
+pub(crate) fn write_bits(bytes: &mut [u8], pos: usize, n: usize, val: u64) {
+    debug_assert!(n <= u64::BITS as usize);
+    debug_assert!(pos + n <= bytes.len() * U8SZ);
+    let start = pos / U8SZ;
+    let shift = pos % U8SZ;
+    let end = (pos + n).div_ceil(U8SZ);
+    let val = val & low_mask(n);
+    let lo_bytes = &mut bytes[start..end.min(start + 8)];
+    let lo = load_word(lo_bytes);
+    let lo_mask = low_mask(n) << shift;
+    store_word(lo_bytes, (lo & !lo_mask) | (val << shift));
+    if end > start + 8 {
+        let hi_mask = low_mask(n + shift - u64::BITS as usize) as u8;
+        let hi = (val >> (u64::BITS as usize - shift)) as u8;
+        bytes[start + 8] = (bytes[start + 8] & !hi_mask) | hi;
+    }
+}
 
 pub unsafe trait GF2MatLike<const ALIGN: usize> {
     fn nrows(&self) -> usize;
@@ -55,6 +183,12 @@ pub unsafe trait GF2MatLike<const ALIGN: usize> {
         self.nchunk_cols() * ALIGN * u8::BITS as usize
     }
 
+    /// The logical number of columns, as opposed to the padded `max_ncols`.
+    /// Bits past this are always zero.
+    fn ncols(&self) -> usize {
+        self.max_ncols()
+    }
+
     fn nbyte_cols(&self) -> usize {
         self.nchunk_cols() * ALIGN
     }
@@ -80,6 +214,11 @@ pub unsafe trait GF2MatLike<const ALIGN: usize> {
         GF2MatWindow {
             nrows,
             nchunk_cols: nchunk_cols,
+            ncols: clipped_ncols(
+                self.ncols(),
+                chunk_col * WINDOW_ALIGN * U8SZ,
+                nchunk_cols * WINDOW_ALIGN * U8SZ,
+            ),
             row_stride: self.row_stride(),
             data: unsafe {
                 self.data_ptr()
@@ -117,10 +256,189 @@ pub unsafe trait GF2MatLike<const ALIGN: usize> {
 
     fn to_mat(&self) -> AlignedGF2Mat<ALIGN>
     where Self: Sized {
-        let mut mat = AlignedGF2Mat::zero(self.nrows(), self.max_ncols());
-        mat.copy_from(self);
+        let mut mat = AlignedGF2Mat::zero(self.nrows(), self.ncols());
+        copy_cols(&mut mat, self);
         mat
     }
+
+    /// The rank of the matrix, computed on a copy. Large matrices go
+    /// through a block PLE decomposition whose updates are done by `addmul`.
+    fn rank(&self) -> usize
+    where Self: Sized {
+        ple(&mut self.to_mat()).rank()
+    }
+
+    /// The determinant, which over GF(2) is whether the matrix is invertible.
+    /// Panics if the matrix is not square.
+    fn det(&self) -> bool
+    where Self: Sized {
+        assert_eq!(self.nrows(), self.ncols(), "only square matrices have a determinant");
+        has_full_col_rank(&mut self.to_mat())
+    }
+
+    /// Whether the matrix is square and invertible. The elimination stops at
+    /// the first column without a pivot, so singular matrices are usually
+    /// rejected early.
+    fn is_invertible(&self) -> bool
+    where Self: Sized {
+        self.nrows() == self.ncols() && has_full_col_rank(&mut self.to_mat())
+    }
+
+    /// The characteristic polynomial, from a Keller-Gehrig style Krylov
+    /// basis whose products are done by `addmul`. Panics if the matrix is
+    /// not square.
+    fn charpoly(&self) -> GF2Poly
+    where Self: Sized {
+        assert_eq!(self.nrows(), self.ncols(), "only square matrices have a characteristic polynomial");
+        crate::charpoly::charpoly(self)
+    }
+
+    /// The minimal polynomial, from the same Krylov basis as `charpoly`.
+    /// Panics if the matrix is not square.
+    fn minpoly(&self) -> GF2Poly
+    where Self: Sized {
+        assert_eq!(self.nrows(), self.ncols(), "only square matrices have a minimal polynomial");
+        crate::charpoly::minpoly(self)
+    }
+
+    /// The Frobenius normal form with its transformation matrix. Panics if
+    /// the matrix is not square.
+    fn frobenius_form(&self) -> FrobeniusForm<ALIGN>
+    where Self: Sized {
+        assert_eq!(self.nrows(), self.ncols(), "only square matrices have a Frobenius form");
+        crate::frobenius::frobenius_form(self)
+    }
+
+    /// A basis of the vectors `x` with `self * x = 0`, as the rows of a
+    /// matrix in reduced row echelon form.
+    fn right_kernel(&self) -> AlignedGF2Mat<ALIGN>
+    where Self: Sized {
+        crate::kernel::right_kernel(self)
+    }
+
+    /// A basis of the vectors `y` with `y * self = 0`, as the rows of a
+    /// matrix in reduced row echelon form.
+    fn left_kernel(&self) -> AlignedGF2Mat<ALIGN>
+    where Self: Sized {
+        crate::kernel::right_kernel(&self.transpose())
+    }
+
+    /// Copies the matrix into an m4ri `BinMatrix`, a word at a time.
+    /// m4ri-rust cannot represent empty matrices, so this panics on them.
+    #[cfg(feature = "m4ri")]
+    fn to_m4ri(&self) -> m4ri_rust::friendly::BinMatrix {
+        crate::m4ri::to_m4ri(self)
+    }
+
+    /// The bit at `(i, j)`
+    ///
+    /// # Safety
+    ///
+    /// `i` has to be below `nrows()` and `j` below `ncols()`.
+    unsafe fn get_unchecked(&self, i: usize, j: usize) -> bool {
+        let byte = unsafe { *self.row_slice_unchecked(i).get_unchecked(j / U8SZ) };
+        (byte >> (j % U8SZ)) & 1 == 1
+    }
+
+    fn try_get(&self, i: usize, j: usize) -> Result<bool, MatAccessError> {
+        if i >= self.nrows() || j >= self.ncols() {
+            Err(MatAccessError::OutOfBounds(i, j, self.nrows(), self.ncols()))
+        } else {
+            Ok(unsafe { self.get_unchecked(i, j) })
+        }
+    }
+
+    /// Like `try_get`, but panics if the position is out of bounds
+    fn get(&self, i: usize, j: usize) -> bool {
+        self.try_get(i, j).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// A single-row window into the `i`-th row
+    fn row(&self, i: usize) -> GF2MatWindow<'_, ALIGN> {
+        if i >= self.nrows() {
+            panic!("{}", MatAccessError::RowOutOfBounds(i, self.nrows()));
+        }
+        unsafe { self.get_window_unchecked::<ALIGN>(i, 0, 1, self.nchunk_cols()) }
+    }
+
+    /// Copies the `j`-th column out into a single-column matrix
+    fn col(&self, j: usize) -> AlignedGF2Mat<ALIGN> {
+        if j >= self.ncols() {
+            panic!("{}", MatAccessError::ColOutOfBounds(j, self.ncols()));
+        }
+        let mut col = AlignedGF2Mat::zero(self.nrows(), 1);
+        for i in 0..self.nrows() {
+            unsafe { col.set_unchecked(i, 0, self.get_unchecked(i, j)) };
+        }
+        col
+    }
+
+    /// A view of the submatrix at `rows` x `cols`, which may be cut at any bit.
+    /// This borrows the data when the columns line up with `ALIGN`-byte chunks
+    /// and copies it otherwise. Panics if the ranges are out of bounds.
+    fn view<R, C>(&self, rows: R, cols: C) -> GF2MatView<'_, ALIGN>
+    where
+        R: RangeBounds<usize>,
+        C: RangeBounds<usize>,
+    {
+        GF2MatView::new(self, rows, cols)
+    }
+
+    /// The transpose as a new matrix
+    fn transpose(&self) -> AlignedGF2Mat<ALIGN> {
+        let mut res = AlignedGF2Mat::zero(self.ncols(), self.nrows());
+        transpose_into(&mut res, self);
+        res
+    }
+
+    fn rows(&self) -> impl Iterator<Item = GF2MatWindow<'_, ALIGN>> + '_ {
+        (0..self.nrows()).map(move |i| self.row(i))
+    }
+
+    /// Compares the logical bits of two matrices a word at a time, they don't
+    /// need to be of the same type or alignment
+    fn mat_eq<const OTHER_ALIGN: usize, S>(&self, other: &S) -> bool
+    where
+        S: GF2MatLike<OTHER_ALIGN> + ?Sized,
+    {
+        if self.nrows() != other.nrows() || self.ncols() != other.ncols() {
+            return false;
+        }
+        (0..self.nrows()).all(|i| {
+            let lhs = unsafe { self.row_slice_unchecked(i) };
+            let rhs = unsafe { other.row_slice_unchecked(i) };
+            masked_row_words(lhs, self.ncols()).eq(masked_row_words(rhs, self.ncols()))
+        })
+    }
+
+    fn is_zero(&self) -> bool {
+        (0..self.nrows()).all(|i| {
+            let row = unsafe { self.row_slice_unchecked(i) };
+            masked_row_words(row, self.ncols()).all(|w| w == 0)
+        })
+    }
+
+    /// Iterates over the positions of all set bits in row-major order.
+    /// Rows are scanned a word at a time, so this is cheap for sparse matrices.
+    fn iter_ones(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let nbytes = self.ncols().div_ceil(U8SZ);
+        (0..self.nrows()).flat_map(move |i| {
+            let row = unsafe { &self.row_slice_unchecked(i)[..nbytes] };
+            row.chunks(u64::BITS as usize / U8SZ)
+                .enumerate()
+                .flat_map(move |(w, bytes)| {
+                    let mut word = load_word(bytes);
+                    std::iter::from_fn(move || {
+                        if word == 0 {
+                            return None;
+                        }
+                        let bit = word.trailing_zeros() as usize;
+                        word &= word - 1;
+                        Some((i, w * u64::BITS as usize + bit))
+                    })
+                })
+        })
+    }
 }
 
 pub unsafe trait GF2MatLikeMut<const ALIGN: usize>: GF2MatLike<ALIGN> {
@@ -217,6 +535,120 @@ pub unsafe trait GF2MatLikeMut<const ALIGN: usize>: GF2MatLike<ALIGN> {
         }
     }
 
+    /// Safe version of `add_unchecked`, adds `rhs` to `self` after checking
+    /// that their dimensions agree.
+    fn add_checked<S>(&mut self, rhs: &S) -> Result<(), MatAddError>
+    where
+        S: GF2MatLike<ALIGN>,
+    {
+        if self.nrows() != rhs.nrows()
+            || self.ncols() != rhs.ncols()
+            || self.nchunk_cols() != rhs.nchunk_cols()
+        {
+            return Err(MatAddError::IncompatibleMatrices(
+                self.nrows(),
+                self.ncols(),
+                rhs.nrows(),
+                rhs.ncols(),
+            ));
+        }
+        if self.nchunk_cols() != 0 {
+            unsafe { self.add_unchecked(rhs) };
+        }
+        Ok(())
+    }
+
+    /// Sets the bit at `(i, j)` to `val`
+    ///
+    /// # Safety
+    ///
+    /// `i` has to be below `nrows()` and `j` below `ncols()`.
+    unsafe fn set_unchecked(&mut self, i: usize, j: usize, val: bool) {
+        let byte = unsafe { self.row_slice_mut_unchecked(i).get_unchecked_mut(j / U8SZ) };
+        if val {
+            *byte |= 1 << (j % U8SZ);
+        } else {
+            *byte &= !(1 << (j % U8SZ));
+        }
+    }
+
+    fn try_set(&mut self, i: usize, j: usize, val: bool) -> Result<(), MatAccessError> {
+        if i >= self.nrows() || j >= self.ncols() {
+            return Err(MatAccessError::OutOfBounds(i, j, self.nrows(), self.ncols()));
+        }
+        unsafe {
+            self.set_unchecked(i, j, val);
+        }
+        Ok(())
+    }
+
+    /// Like `try_set`, but panics if the position is out of bounds
+    fn set(&mut self, i: usize, j: usize, val: bool) {
+        self.try_set(i, j, val).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Flips the bit at `(i, j)`
+    ///
+    /// # Safety
+    ///
+    /// `i` has to be below `nrows()` and `j` below `ncols()`.
+    unsafe fn flip_unchecked(&mut self, i: usize, j: usize) {
+        let byte = unsafe { self.row_slice_mut_unchecked(i).get_unchecked_mut(j / U8SZ) };
+        *byte ^= 1 << (j % U8SZ);
+    }
+
+    /// Flips the bit at `(i, j)`, panics if it is out of bounds
+    fn flip(&mut self, i: usize, j: usize) {
+        if i >= self.nrows() || j >= self.ncols() {
+            panic!("{}", MatAccessError::OutOfBounds(i, j, self.nrows(), self.ncols()));
+        }
+        unsafe { self.flip_unchecked(i, j) };
+    }
+
+    /// Swaps rows `i` and `j`
+    ///
+    /// # Safety
+    ///
+    /// Both `i` and `j` have to be below `nrows()`.
+    unsafe fn swap_rows_unchecked(&mut self, i: usize, j: usize) {
+        debug_assert!(i < self.nrows());
+        debug_assert!(j < self.nrows());
+        if i == j {
+            return;
+        }
+        let len = self.nbyte_cols();
+        let stride = self.row_stride();
+        unsafe {
+            let data = self.data_ptr_mut();
+            std::ptr::swap_nonoverlapping(data.add(i * stride), data.add(j * stride), len);
+        }
+    }
+
+    /// Swaps rows `i` and `j`, panics if either is out of bounds
+    fn swap_rows(&mut self, i: usize, j: usize) {
+        for row in [i, j] {
+            if row >= self.nrows() {
+                panic!("{}", MatAccessError::RowOutOfBounds(row, self.nrows()));
+            }
+        }
+        unsafe { self.swap_rows_unchecked(i, j) };
+    }
+
+    /// Mutable version of `view`. An unaligned view works on a shifted copy
+    /// which is written back into `self` when the view is dropped.
+    fn view_mut<R, C>(&mut self, rows: R, cols: C) -> GF2MatViewMut<'_, ALIGN>
+    where
+        R: RangeBounds<usize>,
+        C: RangeBounds<usize>,
+    {
+        GF2MatViewMut::new(self, rows, cols)
+    }
+
+    /// Transposes the matrix in place, panics if it isn't square
+    fn transpose_in_place(&mut self) {
+        transpose_square_in_place(self);
+    }
+
     fn clear(&mut self) {
         for i in 0..self.nrows() {
             unsafe {
@@ -254,6 +686,11 @@ pub unsafe trait GF2MatLikeMut<const ALIGN: usize>: GF2MatLike<ALIGN> {
         GF2MatWindowMut {
             nrows,
             nchunk_cols,
+            ncols: clipped_ncols(
+                self.ncols(),
+                chunk_col * WINDOW_ALIGN * U8SZ,
+                nchunk_cols * WINDOW_ALIGN * U8SZ,
+            ),
             row_stride: self.row_stride(),
             data: unsafe {
                 self.data_ptr_mut()
@@ -275,11 +712,17 @@ pub unsafe trait GF2MatLikeMut<const ALIGN: usize>: GF2MatLike<ALIGN> {
         debug_assert_eq!(ALIGN % WINDOW_ALIGN, 0);
         debug_assert!(nrows * NBLOCK_ROWS <= self.nrows());
         debug_assert!(nchunk_cols * NBLOCK_COLS <= self.nbyte_cols() / WINDOW_ALIGN);
+        let parent_ncols = self.ncols();
         core::array::from_fn(|block_row| {
             core::array::from_fn(|block_col| {
                 GF2MatWindowMut {
                     nrows: nrows,
                     nchunk_cols: nchunk_cols,
+                    ncols: clipped_ncols(
+                        parent_ncols,
+                        nchunk_cols * block_col * WINDOW_ALIGN * U8SZ,
+                        nchunk_cols * WINDOW_ALIGN * U8SZ,
+                    ),
                     row_stride: self.row_stride(),
                     data: unsafe {
                         (self.data_ptr_mut()).add(
@@ -292,8 +735,31 @@ pub unsafe trait GF2MatLikeMut<const ALIGN: usize>: GF2MatLike<ALIGN> {
             })
         })
     }
+
+    /// Splits the matrix into full width windows of `nrows` rows each, the
+    /// last one having what is left over
+    ///
+    /// # Safety
+    /// `nrows` must not be zero.
+    unsafe fn row_blocks_mut_unchecked(&mut self, nrows: usize) -> Vec<GF2MatWindowMut<'_, ALIGN>> {
+        debug_assert_ne!(nrows, 0);
+        let (total, nchunk_cols, ncols, row_stride) = (self.nrows(), self.nchunk_cols(), self.ncols(), self.row_stride());
+        let data = unsafe { self.data_ptr_mut() };
+        (0..total)
+            .step_by(nrows)
+            .map(|row| GF2MatWindowMut {
+                nrows: nrows.min(total - row),
+                nchunk_cols,
+                ncols,
+                row_stride,
+                data: unsafe { data.add(row * row_stride) },
+                phantom: PhantomData,
+            })
+            .collect()
+    }
 }
 
+#[derive(Clone)]
 pub struct AlignedGF2Mat<const ALIGN: usize> {
     nrows: usize,
     ncols: usize,
@@ -305,9 +771,11 @@ pub struct AlignedGF2Mat<const ALIGN: usize> {
 /// for consitency with the types of the matrix windows
 pub type GF2Mat = AlignedGF2Mat<CACHELINE_SZ>;
 
+#[derive(Clone, Copy)]
 pub struct GF2MatWindow<'a, const ALIGN: usize> {
     nrows: usize,
     nchunk_cols: usize,
+    ncols: usize,
     row_stride: usize,
     data: *const u8,
     phantom: PhantomData<&'a [u8]>,
@@ -316,11 +784,134 @@ pub struct GF2MatWindow<'a, const ALIGN: usize> {
 pub struct GF2MatWindowMut<'a, const ALIGN: usize> {
     nrows: usize,
     nchunk_cols: usize,
+    ncols: usize,
     row_stride: usize,
     data: *mut u8,
     phantom: PhantomData<&'a mut [u8]>,
 }
 
+// SAFETY: a window is a strided borrow of the bytes of a matrix and
+// only ever reads through its pointer, so it can be shared and sent
+// between threads just like the `&'a [u8]` it stands in for.
+unsafe impl<const ALIGN: usize> Send for GF2MatWindow<'_, ALIGN> {}
+unsafe impl<const ALIGN: usize> Sync for GF2MatWindow<'_, ALIGN> {}
+
+// SAFETY: a mutable window is a unique borrow of the rows and columns it
+// covers. The safe constructors never hand out two overlapping mutable
+// windows which are alive at the same time, and the unchecked ones
+// (`get_window_mut_unchecked`, `division_mut_unchecked`) leave that to
+// their callers as part of their contract. It is therefore a `&'a mut [u8]`
+// in disguise, sending it moves the exclusive access to another thread
+// and sharing it only allows reads through `&self`.
+unsafe impl<const ALIGN: usize> Send for GF2MatWindowMut<'_, ALIGN> {}
+unsafe impl<const ALIGN: usize> Sync for GF2MatWindowMut<'_, ALIGN> {}
+
+impl<const ALIGN: usize> Default for AlignedGF2Mat<ALIGN> {
+    /// The empty 0x0 matrix
+    fn default() -> Self {
+        Self::zero(0, 0)
+    }
+}
+
+impl<const ALIGN: usize> Default for GF2MatWindow<'_, ALIGN> {
+    /// An empty window which doesn't point into any matrix
+    fn default() -> Self {
+        GF2MatWindow {
+            nrows: 0,
+            nchunk_cols: 0,
+            ncols: 0,
+            row_stride: 0,
+            data: std::ptr::NonNull::dangling().as_ptr(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<const ALIGN: usize> Default for GF2MatWindowMut<'_, ALIGN> {
+    /// An empty window which doesn't point into any matrix
+    fn default() -> Self {
+        GF2MatWindowMut {
+            nrows: 0,
+            nchunk_cols: 0,
+            ncols: 0,
+            row_stride: 0,
+            data: std::ptr::NonNull::dangling().as_ptr(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, const ALIGN: usize> GF2MatWindow<'a, ALIGN> {
+    /// Splits into the rows before `row` and the rows from `row` on
+    pub fn split_rows(self, row: usize) -> (Self, Self) {
+        assert!(row <= self.nrows, "{}", MatAccessError::RowOutOfBounds(row, self.nrows));
+        let bottom = GF2MatWindow {
+            nrows: self.nrows - row,
+            data: self.data.wrapping_add(row * self.row_stride),
+            ..self
+        };
+        (GF2MatWindow { nrows: row, ..self }, bottom)
+    }
+
+    /// Splits into the chunk columns before `chunk` and the ones from `chunk` on
+    pub fn split_chunk_cols(self, chunk: usize) -> (Self, Self) {
+        assert!(chunk <= self.nchunk_cols);
+        let col = chunk * ALIGN * U8SZ;
+        let right = GF2MatWindow {
+            nchunk_cols: self.nchunk_cols - chunk,
+            ncols: self.ncols.saturating_sub(col),
+            data: self.data.wrapping_add(chunk * ALIGN),
+            ..self
+        };
+        let left = GF2MatWindow {
+            nchunk_cols: chunk,
+            ncols: self.ncols.min(col),
+            ..self
+        };
+        (left, right)
+    }
+}
+
+impl<'a, const ALIGN: usize> GF2MatWindowMut<'a, ALIGN> {
+    /// A shorter lived window over the same data, e.g. for splitting it
+    /// without giving up `self`
+    pub fn reborrow(&mut self) -> GF2MatWindowMut<'_, ALIGN> {
+        GF2MatWindowMut {
+            phantom: PhantomData,
+            ..*self
+        }
+    }
+
+    /// Splits into the rows before `row` and the rows from `row` on
+    pub fn split_rows_mut(self, row: usize) -> (Self, Self) {
+        assert!(row <= self.nrows, "{}", MatAccessError::RowOutOfBounds(row, self.nrows));
+        let bottom = GF2MatWindowMut {
+            nrows: self.nrows - row,
+            data: self.data.wrapping_add(row * self.row_stride),
+            ..self
+        };
+        (GF2MatWindowMut { nrows: row, ..self }, bottom)
+    }
+
+    /// Splits into the chunk columns before `chunk` and the ones from `chunk` on
+    pub fn split_chunk_cols_mut(self, chunk: usize) -> (Self, Self) {
+        assert!(chunk <= self.nchunk_cols);
+        let col = chunk * ALIGN * U8SZ;
+        let right = GF2MatWindowMut {
+            nchunk_cols: self.nchunk_cols - chunk,
+            ncols: self.ncols.saturating_sub(col),
+            data: self.data.wrapping_add(chunk * ALIGN),
+            ..self
+        };
+        let left = GF2MatWindowMut {
+            nchunk_cols: chunk,
+            ncols: self.ncols.min(col),
+            ..self
+        };
+        (left, right)
+    }
+}
+
 unsafe impl<const ALIGN: usize> GF2MatLike<ALIGN> for AlignedGF2Mat<ALIGN> {
     #[inline(always)]
     fn nrows(&self) -> usize {
@@ -332,6 +923,11 @@ unsafe impl<const ALIGN: usize> GF2MatLike<ALIGN> for AlignedGF2Mat<ALIGN> {
         self.row_stride / ALIGN
     }
 
+    #[inline(always)]
+    fn ncols(&self) -> usize {
+        self.ncols
+    }
+
     #[inline(always)]
     fn row_stride(&self) -> usize {
         self.row_stride
@@ -356,6 +952,9 @@ unsafe impl<const ALIGN: usize> GF2MatLike<ALIGN> for GF2MatWindow<'_, ALIGN> {
     fn nchunk_cols(&self) -> usize {
         self.nchunk_cols
     }
+    fn ncols(&self) -> usize {
+        self.ncols
+    }
 
     fn data_ptr(&self) -> *const u8 {
         self.data
@@ -373,6 +972,9 @@ unsafe impl<const ALIGN: usize> GF2MatLike<ALIGN> for GF2MatWindowMut<'_, ALIGN>
     fn nchunk_cols(&self) -> usize {
         self.nchunk_cols
     }
+    fn ncols(&self) -> usize {
+        self.ncols
+    }
 
     fn row_stride(&self) -> usize {
         self.row_stride
@@ -402,95 +1004,202 @@ impl<const ALIGN: usize> AlignedGF2Mat<ALIGN> {
         }
     }
 
-    pub fn random(nrows: usize, ncols: usize, rng: &mut ThreadRng) -> Self {
-        let mut mat = Self::zero(nrows, ncols);
-        mat.data.iter_mut().for_each(|x| *x = rng.gen::<u8>());
-        // zero out edges
-
-        let mask = match ncols % u8::BITS as usize {
-            0 => 0xffu8,
-            rem => (1u8 << rem) - 1,
-        };
-
-        let last_limb: usize = (ncols - 1) / u8::BITS as usize;
-        for i in 0..nrows {
-            mat.data[i * mat.row_stride + last_limb] &= mask;
-            for j in ncols.div_ceil(u8::BITS as usize)..mat.row_stride {
-                mat.data[i * mat.row_stride + j] = 0;
-            }
+    pub fn identity(n: usize) -> Self {
+        let mut mat = Self::zero(n, n);
+        for i in 0..n {
+            unsafe { mat.set_unchecked(i, i, true) };
         }
-
         mat
     }
 
-    pub fn from_m4ri(bm: &BinMatrix) -> Self {
-        let nrows = bm.nrows();
-        let ncols = bm.ncols();
-        let mut res = AlignedGF2Mat::<ALIGN>::zero(nrows, ncols);
+    pub fn from_fn<F>(nrows: usize, ncols: usize, mut f: F) -> Self
+    where
+        F: FnMut(usize, usize) -> bool,
+    {
+        let mut mat = Self::zero(nrows, ncols);
         for i in 0..nrows {
             for j in 0..ncols {
-                if bm.bit(i as usize, j as usize) {
-                    res.try_set(i, j, true).unwrap();
+                if f(i, j) {
+                    unsafe { mat.set_unchecked(i, j, true) };
                 }
             }
         }
-        res
+        mat
     }
 
-    unsafe fn get_unchecked(&self, i: usize, j: usize) -> bool {
-        let byte_idx = j / u8::BITS as usize;
-        let bit_idx = j % u8::BITS as usize;
-        let byte = unsafe { self.row_slice_unchecked(i).get_unchecked(byte_idx) };
-        (byte >> bit_idx) & 1 == 1
+    /// Builds a matrix from rows of bools, panics if they differ in length
+    pub fn from_rows<R: AsRef<[bool]>>(rows: &[R]) -> Self {
+        let ncols = rows.first().map_or(0, |row| row.as_ref().len());
+        let mut mat = Self::zero(rows.len(), ncols);
+        for (i, row) in rows.iter().enumerate() {
+            let row = row.as_ref();
+            assert_eq!(row.len(), ncols, "row {i} has a different length than the first");
+            for (j, &bit) in row.iter().enumerate() {
+                if bit {
+                    unsafe { mat.set_unchecked(i, j, true) };
+                }
+            }
+        }
+        mat
     }
 
-    fn try_get(&self, i: usize, j: usize) -> Result<bool, MatAccessError> {
-        if i >= self.nrows || j >= self.ncols {
-            Err(MatAccessError::OutOfBounds(i, j, self.nrows, self.ncols))
-        } else {
-            Ok(unsafe { self.get_unchecked(i, j) })
+    /// Builds a matrix with `ncols` columns from rows packed into words,
+    /// column `j` being bit `j % 64` of word `j / 64`. Every row needs
+    /// exactly `ncols.div_ceil(64)` words, bits past `ncols` are ignored.
+    pub fn from_words<R: AsRef<[u64]>>(ncols: usize, rows: &[R]) -> Self {
+        let nwords = ncols.div_ceil(u64::BITS as usize);
+        let mut mat = Self::zero(rows.len(), ncols);
+        for (i, row) in rows.iter().enumerate() {
+            let row = row.as_ref();
+            assert_eq!(row.len(), nwords, "row {i} should have {nwords} words");
+            let dst = unsafe { mat.row_slice_mut_unchecked(i) };
+            for (dst, &word) in dst.chunks_mut(u64::BITS as usize / U8SZ).zip(row) {
+                store_word(dst, word);
+            }
         }
+        mat.clear_padding();
+        mat
     }
 
-    unsafe fn set_unchecked(&mut self, i: usize, j: usize, val: bool) {
-        let byte_idx = j / u8::BITS as usize;
-        let bit_idx = j % u8::BITS as usize;
-        let byte = unsafe { self.row_slice_mut_unchecked(i).get_unchecked_mut(byte_idx) };
-        if val {
-            *byte |= 1 << bit_idx;
-        } else {
-            *byte &= !(1 << bit_idx);
-        }
+    pub fn random<R: Rng + ?Sized>(nrows: usize, ncols: usize, rng: &mut R) -> Self {
+        let mut mat = Self::zero(nrows, ncols);
+        mat.data.iter_mut().for_each(|x| *x = rng.gen::<u8>());
+        mat.clear_padding();
+        mat
     }
 
-    fn try_set(&mut self, i: usize, j: usize, val: bool) -> Result<(), MatAccessError> {
-        if i >= self.nrows || j >= self.ncols {
-            return Err(MatAccessError::OutOfBounds(i, j, self.nrows, self.ncols));
+    /// A random matrix where every entry is set independently with probability
+    /// `density`. Only the set bits (or the clear ones, for densities above 1/2)
+    /// are sampled, so sparse matrices are cheap to generate.
+    pub fn random_with<R: Rng + ?Sized>(nrows: usize, ncols: usize, rng: &mut R, density: f64) -> Self {
+        assert!((0.0..=1.0).contains(&density), "density {density} is not a probability");
+        if density == 0.5 {
+            return Self::random(nrows, ncols, rng);
         }
-        unsafe {
-            self.set_unchecked(i, j, val);
+        let mut mat = Self::zero(nrows, ncols);
+        let flipped = density > 0.5;
+        let p = if flipped { 1.0 - density } else { density };
+        if flipped {
+            mat.data.fill(0xff);
+            mat.clear_padding();
+        }
+        // the gaps between successive hits are geometrically distributed,
+        // `ln_1p` keeps `log_q` from rounding to zero for tiny `p`
+        let log_q = (-p).ln_1p();
+        if log_q == 0.0 {
+            return mat;
+        }
+        let len = nrows * ncols;
+        let mut idx = 0usize;
+        loop {
+            let u: f64 = 1.0 - rng.gen::<f64>();
+            let gap = (u.ln() / log_q).floor();
+            if !gap.is_finite() || gap >= (len - idx) as f64 {
+                break;
+            }
+            idx += gap as usize;
+            unsafe { mat.flip_unchecked(idx / ncols, idx % ncols) };
+            idx += 1;
+        }
+        mat
+    }
+
+    /// Zeroes the bits past `ncols` in every row, the multiplication
+    /// kernels rely on these never being set.
+    pub(crate) fn clear_padding(&mut self) {
+        let used_bytes = self.ncols.div_ceil(u8::BITS as usize);
+        let mask = match self.ncols % u8::BITS as usize {
+            0 => 0xffu8,
+            rem => (1u8 << rem) - 1,
+        };
+        for i in 0..self.nrows {
+            let row = &mut self.data[i * self.row_stride..(i + 1) * self.row_stride];
+            if used_bytes > 0 {
+                row[used_bytes - 1] &= mask;
+            }
+            row[used_bytes..].fill(0);
         }
-        Ok(())
     }
 }
 
-impl PartialEq for GF2Mat {
-    fn eq(&self, other: &GF2Mat) -> bool {
-        if self.nrows != other.nrows || self.ncols != other.ncols {
-            return false;
+/// Copies the columns of `src` into `dst`, which has the same dimensions
+/// but possibly fewer or more chunk columns, and zeroes the padding of `dst`
+pub(crate) fn copy_cols<const ALIGN: usize, T, S>(dst: &mut T, src: &S)
+where
+    T: GF2MatLikeMut<ALIGN> + ?Sized,
+    S: GF2MatLike<ALIGN> + ?Sized,
+{
+    debug_assert!(dst.nrows() == src.nrows());
+    debug_assert!(dst.ncols() == src.ncols());
+    let nbytes = src.ncols().div_ceil(U8SZ);
+    let mask = match src.ncols() % U8SZ {
+        0 => 0xffu8,
+        rem => (1u8 << rem) - 1,
+    };
+    for i in 0..src.nrows() {
+        let src_row = unsafe { src.row_slice_unchecked(i) };
+        let dst_row = unsafe { dst.row_slice_mut_unchecked(i) };
+        dst_row[..nbytes].copy_from_slice(&src_row[..nbytes]);
+        if nbytes > 0 {
+            dst_row[nbytes - 1] &= mask;
         }
-        // TODO: optimize
-        for i in 0..self.nrows {
-            for j in 0..self.ncols {
-                if self.try_get(i, j).unwrap() != other.try_get(i, j).unwrap() {
-                    return false;
+        dst_row[nbytes..].fill(0);
+    }
+}
+
+/// The words of a row with the bits past `ncols` masked off, so that rows
+/// can be compared regardless of what their padding holds
+fn masked_row_words(row: &[u8], ncols: usize) -> impl Iterator<Item = u64> + '_ {
+    let nbytes = ncols.div_ceil(U8SZ);
+    row[..nbytes]
+        .chunks(u64::BITS as usize / U8SZ)
+        .enumerate()
+        .map(move |(w, bytes)| {
+            load_word(bytes) & low_mask((ncols - w * u64::BITS as usize).min(u64::BITS as usize))
+        })
+}
+
+macro_rules! impl_eq_hash {
+    ($($lhs:ty),*; $rhs:tt) => {$(
+        impl_eq_hash!(@eq $lhs; $rhs);
+
+        impl<const A: usize> Eq for $lhs {}
+
+        impl<const A: usize> Hash for $lhs {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                state.write_usize(self.nrows());
+                state.write_usize(self.ncols());
+                for i in 0..self.nrows() {
+                    let row = unsafe { self.row_slice_unchecked(i) };
+                    masked_row_words(row, self.ncols()).for_each(|w| state.write_u64(w));
                 }
             }
         }
-        true
-    }
+    )*};
+    (@eq $lhs:ty; [$($rhs:ty),*]) => {$(
+        impl<const A: usize, const B: usize> PartialEq<$rhs> for $lhs {
+            fn eq(&self, other: &$rhs) -> bool {
+                self.mat_eq(other)
+            }
+        }
+    )*};
 }
 
+impl_eq_hash!(
+    AlignedGF2Mat<A>,
+    GF2MatWindow<'_, A>,
+    GF2MatWindowMut<'_, A>,
+    GF2MatView<'_, A>,
+    GF2MatViewMut<'_, A>;
+    [
+        AlignedGF2Mat<B>,
+        GF2MatWindow<'_, B>,
+        GF2MatWindowMut<'_, B>,
+        GF2MatView<'_, B>,
+        GF2MatViewMut<'_, B>
+    ]
+);
+
 /*
 For these functions we use hint::assert_unchecked to remove
 edge cases which result in branches in the generated code.
@@ -528,6 +1237,40 @@ pub unsafe fn xor_aligned_slices_to<const ALIGN: usize>(dst: &mut [u8], src1: &[
     }
 }
 
+/// `dst &= src` bytewise
+///
+/// # Safety
+///
+/// The same as for `xor_aligned_slices`: the byte sizes of `dst` and `src`
+/// have to be non-zero, equal and multiples of `ALIGN`.
+pub unsafe fn and_aligned_slices<const ALIGN: usize>(dst: &mut [u8], src: &[u8]) {
+    unsafe { std::hint::assert_unchecked(!dst.is_empty()) };
+    unsafe { std::hint::assert_unchecked(!src.is_empty()) };
+    unsafe { std::hint::assert_unchecked(dst.len().is_multiple_of(ALIGN)) };
+    unsafe { std::hint::assert_unchecked(src.len().is_multiple_of(ALIGN)) };
+    unsafe { std::hint::assert_unchecked(dst.len() == src.len()) };
+    for (d, s) in std::iter::zip(dst, src) {
+        *d &= *s;
+    }
+}
+
+/// `dst |= src` bytewise
+///
+/// # Safety
+///
+/// The same as for `xor_aligned_slices`: the byte sizes of `dst` and `src`
+/// have to be non-zero, equal and multiples of `ALIGN`.
+pub unsafe fn or_aligned_slices<const ALIGN: usize>(dst: &mut [u8], src: &[u8]) {
+    unsafe { std::hint::assert_unchecked(!dst.is_empty()) };
+    unsafe { std::hint::assert_unchecked(!src.is_empty()) };
+    unsafe { std::hint::assert_unchecked(dst.len().is_multiple_of(ALIGN)) };
+    unsafe { std::hint::assert_unchecked(src.len().is_multiple_of(ALIGN)) };
+    unsafe { std::hint::assert_unchecked(dst.len() == src.len()) };
+    for (d, s) in std::iter::zip(dst, src) {
+        *d |= *s;
+    }
+}
+
 impl<const ALIGN: usize> Debug for AlignedGF2Mat<ALIGN> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "[")?;
@@ -0,0 +1,96 @@
+/*!
+Matrix inversion.
+
+Small matrices are inverted by Gauss-Jordan elimination of `[A | I]` with the
+Four-Russians tables of `echelonize`. Larger ones are decomposed as
+`A = P * L * U` with the block PLE decomposition, after which
+`A^-1 = U^-1 * L^-1 * P^-1` is obtained by solving two triangular systems
+against the permuted identity. Both solves split recursively and do their
+updates with `addmul`, so inversion costs a small multiple of a product.
+*/
+
+use crate::echelon::echelonize;
+use crate::gf2mat::*;
+use crate::mul::WINDOW_ALIGN;
+use crate::ple::{apply_swaps, ple, trsm_lower_compact};
+use crate::trsm::{trsm_unchecked, Variant};
+
+const U8SZ: usize = u8::BITS as usize;
+/// Matrices with at most this many rows are inverted by Gauss-Jordan
+const INV_CUTOFF: usize = 1024;
+
+/// Gauss-Jordan on `[A | I]`, with `I` starting at the first chunk past `A`
+fn inverse_m4ri<const ALIGN: usize>(mat: &AlignedGF2Mat<ALIGN>) -> Option<AlignedGF2Mat<ALIGN>> {
+    let n = mat.nrows();
+    let off = mat.nbyte_cols();
+    let mut aug = AlignedGF2Mat::<ALIGN>::zero(n, off * U8SZ + n);
+    for i in 0..n {
+        let row = unsafe { aug.row_slice_mut_unchecked(i) };
+        row[..off].copy_from_slice(unsafe { mat.row_slice_unchecked(i) });
+        unsafe { aug.set_unchecked(i, off * U8SZ + i, true) };
+    }
+    echelonize(&mut aug, true);
+    // the pivots of the reduced form are increasing, so the last row has
+    // its pivot on the diagonal exactly when all rows of A have one
+    if !unsafe { aug.get_unchecked(n - 1, n - 1) } {
+        return None;
+    }
+    let mut inv = AlignedGF2Mat::<ALIGN>::zero(n, n);
+    for i in 0..n {
+        unsafe { inv.row_slice_mut_unchecked(i) }
+            .copy_from_slice(unsafe { &aug.row_slice_unchecked(i)[off..2 * off] });
+    }
+    Some(inv)
+}
+
+/// `U^-1 * L^-1 * P^-1` from the PLE decomposition
+fn inverse_ple<const ALIGN: usize>(mat: &AlignedGF2Mat<ALIGN>) -> Option<AlignedGF2Mat<ALIGN>> {
+    let n = mat.nrows();
+    let mut lu = mat.clone();
+    let perms = ple(&mut lu);
+    if perms.rank() != n {
+        return None;
+    }
+    let mut inv = AlignedGF2Mat::<ALIGN>::identity(n);
+    apply_swaps(&mut inv, &perms.rows);
+    let nchunks = lu.nchunk_cols() * ALIGN / WINDOW_ALIGN;
+    let lu = unsafe { lu.get_window_unchecked::<WINDOW_ALIGN>(0, 0, n, nchunks) };
+    let mut b = unsafe { inv.get_window_mut_unchecked::<WINDOW_ALIGN>(0, 0, n, nchunks) };
+    // full rank, so the pivots and with them the columns of L are 0..n
+    unsafe {
+        trsm_lower_compact(lu, &perms.rank_profile, &mut b);
+        trsm_unchecked(Variant::UpperLeft, &lu, &mut b);
+    }
+    Some(inv)
+}
+
+impl<const ALIGN: usize> AlignedGF2Mat<ALIGN> {
+    fn try_inverse(&self) -> Result<Self, MatInvError> {
+        let (nrows, ncols) = (self.nrows(), self.ncols());
+        if nrows != ncols {
+            return Err(MatInvError::NotSquare(nrows, ncols));
+        }
+        if nrows == 0 {
+            return Ok(Self::zero(0, 0));
+        }
+        let inv = if nrows <= INV_CUTOFF || !ALIGN.is_multiple_of(WINDOW_ALIGN) {
+            inverse_m4ri(self)
+        } else {
+            inverse_ple(self)
+        };
+        inv.ok_or(MatInvError::Singular(nrows))
+    }
+
+    /// The inverse of the matrix, `None` if it is singular or not square
+    pub fn inverse(&self) -> Option<Self> {
+        self.try_inverse().ok()
+    }
+
+    /// Replaces the matrix with its inverse. This is [`Self::inverse`] under
+    /// the hood, so the inverse is computed into a new allocation which then
+    /// takes the place of the old one. On error the matrix is left unchanged.
+    pub fn invert(&mut self) -> Result<(), MatInvError> {
+        *self = self.try_inverse()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,42 @@
+/*!
+Kernels, also known as nullspaces.
+
+With `A * Q^-1 = P * L * [U1 U2]` from `pluq` and `L` of full column rank,
+`A * x = 0` holds exactly when `[U1 U2] * Q * x = 0`. Writing `Q * x` as
+`[y; z]`, the free part `z` can be anything and `y = U1^-1 * U2 * z`, so the
+columns of `Q^-1 * [U1^-1 * U2; I]` span the right kernel. `U1^-1 * U2` is a
+triangular solve whose updates are done by `addmul`. The basis is returned
+as the rows of a matrix and brought into reduced echelon form, which makes
+it unique. The left kernel is the right kernel of the transpose.
+*/
+
+use crate::echelon::echelonize;
+use crate::gf2mat::*;
+use crate::ple::{apply_col_swaps, pluq};
+use crate::trsm::{trsm_unchecked, Variant};
+
+/// See `GF2MatLike::right_kernel`
+pub(crate) fn right_kernel<const ALIGN: usize, S>(mat: &S) -> AlignedGF2Mat<ALIGN>
+where
+    S: GF2MatLike<ALIGN>,
+{
+    let n = mat.ncols();
+    let mut lu = mat.to_mat();
+    let perms = pluq(&mut lu);
+    let r = perms.rank();
+
+    let mut free = lu.view(..r, r..).to_mat();
+    unsafe { trsm_unchecked(Variant::UpperLeft, &lu.view(..r, ..r).to_mat(), &mut free) };
+    let free = free.transpose();
+
+    let mut ker = AlignedGF2Mat::<ALIGN>::zero(n - r, n);
+    for i in 0..n - r {
+        let src = unsafe { free.row_slice_unchecked(i) };
+        let dst = unsafe { ker.row_slice_mut_unchecked(i) };
+        dst[..src.len()].copy_from_slice(src);
+        unsafe { ker.set_unchecked(i, r + i, true) };
+    }
+    apply_col_swaps(&mut ker, &perms.cols[..r], true);
+    echelonize(&mut ker, true);
+    ker
+}
@@ -4,28 +4,56 @@
 #![warn(unsafe_op_in_unsafe_fn)]
 #![allow(dead_code)]
 
+mod charpoly;
 mod decomp2x2;
+#[cfg(feature = "decomp3x3")]
 mod decomp3x3;
 mod decomp4x4;
+#[cfg(feature = "decomp5x5")]
 mod decomp5x5;
 
+mod echelon;
+mod frobenius;
 mod gf2mat;
+mod inverse;
+mod kernel;
 mod m4rm;
 mod mul;
+mod ops;
+mod parallel;
+mod plan;
+mod ple;
+mod poly;
+mod pow;
+mod solve;
+mod transpose;
+mod trsm;
+mod tune;
+mod view;
+#[cfg(feature = "m4ri")]
+mod m4ri;
 mod decomp_macro;
 
 pub use m4rm::*;
 pub use gf2mat::*;
-pub use mul::addmul;
+pub use view::*;
+pub use echelon::echelonize;
+pub use frobenius::{is_similar, FrobeniusForm};
+pub use ple::{ple, pluq, LuPermutations};
+pub use poly::GF2Poly;
+pub use pow::Exponent;
+pub use solve::{solve, solve_left};
+pub use trsm::{trsm_lower_left, trsm_lower_right, trsm_upper_left, trsm_upper_right, Diag};
+pub use mul::{addmul, addmul_checked, addmul_par, addmul_par_checked, mul, mul_into, mul_par, AddMulAlgo};
+pub use tune::{TuneOptions, TuningProfile, WINDOW_ALIGNS};
 
 extern crate test;
 #[cfg(test)]
 mod tests {
+    #[cfg(feature = "m4ri")]
     use m4ri_rust::friendly::BinMatrix;
     use rand::Rng;
 
-    use crate::mul::addmul;
-
     use super::*;
 
     const MAT_TEST_SIZES: &'static [usize] = &[
@@ -35,6 +63,40 @@ mod tests {
         256, 512, 513, 647
     ];
 
+    /// Schoolbook product, the reference for products checked without m4ri
+    fn naive_mul(a: &GF2Mat, b: &GF2Mat) -> GF2Mat {
+        let mut res = GF2Mat::zero(a.nrows(), b.ncols());
+        for (i, k) in a.iter_ones() {
+            // a word at a time, so unoptimized builds get through the
+            // Strassen sizes in reasonable time
+            let (_, src, _) = unsafe { b.row_slice_unchecked(k).align_to::<u64>() };
+            let (_, dst, _) = unsafe { res.row_slice_mut_unchecked(i).align_to_mut::<u64>() };
+            for (d, s) in dst.iter_mut().zip(src) {
+                *d ^= s;
+            }
+        }
+        res
+    }
+
+    /// Textbook Gauss-Jordan, returns the reduced row echelon form and the rank
+    fn naive_rref(a: &GF2Mat) -> (GF2Mat, usize) {
+        let mut a = a.clone();
+        let mut r = 0;
+        for j in 0..a.ncols() {
+            let Some(p) = (r..a.nrows()).find(|&i| a.get(i, j)) else { continue };
+            a.swap_rows(p, r);
+            for i in 0..a.nrows() {
+                if i != r && a.get(i, j) {
+                    let (dst, src) = unsafe { a.distinct_row_slices_mut_immut(i, r) };
+                    unsafe { xor_aligned_slices::<128>(dst, src) };
+                }
+            }
+            r += 1;
+        }
+        (a, r)
+    }
+
+    #[cfg(feature = "m4ri")]
     #[test]
     fn test_mul_strassen() {
         let mut rng = rand::thread_rng();
@@ -60,6 +122,7 @@ mod tests {
     }
 
 
+    #[cfg(feature = "m4ri")]
     #[test]
     fn test_mul_consistent() {
         for mat_sz in MAT_TEST_SIZES {
@@ -90,4 +153,1072 @@ mod tests {
             assert!(prod == GF2Mat::from_m4ri(&tgt));
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_mul_strassen_naive() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..1 {
+            // at least STRASSEN_CUTOFF, so the decompositions are used
+            let n = rng.gen_range(4096..4352);
+            let k = rng.gen_range(4096..4352);
+            let m = rng.gen_range(4096..4352);
+
+            let m1 = GF2Mat::random(n, k, &mut rng);
+            let m2 = GF2Mat::random(k, m, &mut rng);
+            let mut diff = GF2Mat::zero(n, m);
+            unsafe { addmul(&mut diff, &m1, &m2); }
+            let tgt = naive_mul(&m1, &m2);
+            unsafe { diff.add_unchecked(&tgt); }
+            assert!(diff == GF2Mat::zero(n, m));
+        }
+    }
+
+
+    #[test]
+    fn test_mul_consistent_naive() {
+        let mut rng = rand::thread_rng();
+        for mat_sz in MAT_TEST_SIZES {
+            println!("Testing mat_sz: {}", mat_sz);
+            let m1 = GF2Mat::random(*mat_sz, *mat_sz, &mut rng);
+            let m2 = GF2Mat::random(*mat_sz, *mat_sz, &mut rng);
+            let tgt = naive_mul(&m1, &m2);
+            let mut diff = GF2Mat::zero(*mat_sz, *mat_sz);
+            unsafe { addmul(&mut diff, &m1, &m2); }
+            unsafe { diff.add_unchecked(&tgt); }
+            assert!(diff == GF2Mat::zero(*mat_sz, *mat_sz));
+        }
+
+        for _ in 0..100 {
+            let nrows = rng.gen_range(1..512);
+            let conn = rng.gen_range(1..512);
+            let ncols = rng.gen_range(1..512);
+            let m1 = GF2Mat::random(nrows, conn, &mut rng);
+            let m2 = GF2Mat::random(conn, ncols, &mut rng);
+            let mut prod = GF2Mat::zero(nrows, ncols);
+            unsafe { addmul(&mut prod, &m1, &m2); }
+            assert!(prod == naive_mul(&m1, &m2));
+        }
+    }
+
+    #[test]
+    fn test_mul_checked() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..20 {
+            let nrows = rng.gen_range(1..300);
+            let conn = rng.gen_range(1..300);
+            let ncols = rng.gen_range(1..300);
+            let m1 = GF2Mat::random(nrows, conn, &mut rng);
+            let m2 = GF2Mat::random(conn, ncols, &mut rng);
+            let tgt = naive_mul(&m1, &m2);
+            assert!(mul(&m1, &m2).unwrap() == tgt);
+
+            let mut prod = GF2Mat::random(nrows, ncols, &mut rng);
+            mul_into(&mut prod, &m1, &m2).unwrap();
+            assert!(prod == tgt);
+            addmul_checked(&mut prod, &m1, &m2).unwrap();
+            assert!(prod == GF2Mat::zero(nrows, ncols));
+        }
+
+        // the padded widths agree here, only the logical ones don't
+        let m1 = GF2Mat::zero(10, 20);
+        let m2 = GF2Mat::zero(21, 30);
+        assert!(matches!(mul(&m1, &m2), Err(MatMulError::IncompatibleMatrices(10, 20, 21, 30))));
+        let m2 = GF2Mat::zero(20, 30);
+        let mut prod = GF2Mat::zero(10, 31);
+        assert!(matches!(mul_into(&mut prod, &m1, &m2), Err(MatMulError::DimMismatch(10, 30, 10, 31))));
+        let mut prod = GF2Mat::zero(11, 30);
+        assert!(matches!(addmul_checked(&mut prod, &m1, &m2), Err(MatMulError::DimMismatch(10, 30, 11, 30))));
+
+        let empty = GF2Mat::zero(10, 0);
+        assert!(mul(&empty, &GF2Mat::zero(0, 5)).unwrap() == GF2Mat::zero(10, 5));
+    }
+
+    #[test]
+    fn test_mul_par() {
+        let mut rng = rand::thread_rng();
+        // large enough to recurse, with leftover rows and columns to peel
+        let (nrows, conn, ncols) = (rng.gen_range(4096..5000), rng.gen_range(4096..5000), rng.gen_range(4096..5000));
+        let m1 = GF2Mat::random(nrows, conn, &mut rng);
+        let m2 = GF2Mat::random(conn, ncols, &mut rng);
+        let tgt = mul(&m1, &m2).unwrap();
+        for threads in [0, 3, 8] {
+            assert!(mul_par(&m1, &m2, threads).unwrap() == tgt);
+        }
+        let mut prod = tgt.clone();
+        addmul_par_checked(&mut prod, &m1, &m2, 5).unwrap();
+        assert!(prod == GF2Mat::zero(nrows, ncols));
+
+        let m1 = GF2Mat::random(100, 200, &mut rng);
+        let m2 = GF2Mat::random(200, 50, &mut rng);
+        assert!(mul_par(&m1, &m2, 4).unwrap() == naive_mul(&m1, &m2));
+        assert!(matches!(mul_par(&m2, &m2, 4), Err(MatMulError::IncompatibleMatrices(200, 50, 200, 50))));
+    }
+
+    #[test]
+    fn test_m4rm_par() {
+        let mut rng = rand::thread_rng();
+        // tall times wide, with more tables than fit in a batch
+        let (nrows, conn, ncols) = (rng.gen_range(2000..4000), rng.gen_range(600..1500), rng.gen_range(1500..2500));
+        let m1 = GF2Mat::random(nrows, conn, &mut rng);
+        let m2 = GF2Mat::random(conn, ncols, &mut rng);
+        let init = GF2Mat::random(nrows, ncols, &mut rng);
+        let mut tgt = init.clone();
+        unsafe { addmul_m4rm(&mut tgt, &m1, &m2) };
+        for threads in [2, 3, 7] {
+            let mut prod = init.clone();
+            unsafe { addmul_m4rm_par(&mut prod, &m1, &m2, threads) };
+            assert!(prod == tgt);
+        }
+        assert!(&mul_par(&m1, &m2, 5).unwrap() + &init == tgt);
+    }
+
+    #[cfg(any(feature = "decomp3x3", feature = "decomp5x5"))]
+    #[test]
+    fn test_decomp_odd() {
+        use crate::mul::{addmul_recurse, addmul_recurse_par, AddMulAlgo};
+        use crate::plan::plan_addmul;
+        use crate::tune::TuningProfile;
+        let mut rng = rand::thread_rng();
+        let algos = [
+            #[cfg(feature = "decomp3x3")]
+            (AddMulAlgo::Decomp3x3, 3),
+            #[cfg(feature = "decomp5x5")]
+            (AddMulAlgo::Decomp5x5, 5),
+        ];
+        for (algo, dim) in algos {
+            // through windows, as GF2Mat pads the width past what divides evenly
+            let n = dim * 256;
+            let m1 = GF2Mat::random(n, n, &mut rng);
+            let m2 = GF2Mat::random(n, n, &mut rng);
+            let tgt = naive_mul(&m1, &m2);
+            let lhs = unsafe { m1.get_window_unchecked::<16>(0, 0, n, n / 128) };
+            let rhs = unsafe { m2.get_window_unchecked::<16>(0, 0, n, n / 128) };
+            let mut prod = GF2Mat::zero(n, n);
+            let mut prod_window = unsafe { prod.get_window_mut_unchecked::<16>(0, 0, n, n / 128) };
+            unsafe { addmul_recurse(&mut prod_window, &lhs, &rhs, &[algo, AddMulAlgo::M4RM]) };
+            assert!(prod == tgt);
+            let mut prod = GF2Mat::zero(n, n);
+            let mut prod_window = unsafe { prod.get_window_mut_unchecked::<16>(0, 0, n, n / 128) };
+            unsafe { addmul_recurse_par(&mut prod_window, &lhs, &rhs, &[algo, AddMulAlgo::Decomp2x2, AddMulAlgo::M4RM], 3) };
+            assert!(prod == tgt);
+        }
+
+        // no peeling with 3x3 on top, while 2x2 leaves strips of 128
+        #[cfg(feature = "decomp3x3")]
+        assert!(plan_addmul(6144 + 384, 6144 + 384, 6144 + 384, 16, &TuningProfile::default()).algos.contains(&AddMulAlgo::Decomp3x3));
+    }
+
+    #[test]
+    fn test_plan() {
+        use crate::mul::{addmul_planned, AddMulAlgo, STRASSEN_CUTOFF};
+        use crate::plan::{plan_addmul, Plan};
+        use crate::tune::TuningProfile;
+        for (m, k, n) in [(4096, 4096, 4096), (6000, 6000, 6144), (12288, 12288, 12288), (5000, 9000, 7168), (20000, 4500, 5120)] {
+            let plan = plan_addmul(m, k, n, 16, &TuningProfile::default());
+            assert!(plan.algos.last() == Some(&AddMulAlgo::M4RM));
+            let blocks: usize = plan.algos.iter().map(|algo| algo.dim()).product();
+            assert!(plan.recurse_sz == blocks * 128);
+            assert!(m.min(k).min(n) / blocks <= STRASSEN_CUTOFF);
+            // padding is only worth anything if some dimension doesn't divide
+            assert!(!plan.pad || [m, k, n].iter().any(|x| x % plan.recurse_sz != 0));
+        }
+
+        let mut rng = rand::thread_rng();
+        let (nrows, conn, ncols) = (rng.gen_range(600..900), rng.gen_range(600..900), rng.gen_range(600..900));
+        let m1 = GF2Mat::random(nrows, conn, &mut rng);
+        let m2 = GF2Mat::random(conn, ncols, &mut rng);
+        let init = GF2Mat::random(nrows, ncols, &mut rng);
+        let tgt = &init + &naive_mul(&m1, &m2);
+        let plans = [
+            (vec![AddMulAlgo::Decomp2x2, AddMulAlgo::M4RM], 256, false),
+            (vec![AddMulAlgo::Decomp2x2, AddMulAlgo::M4RM], 256, true),
+            (vec![AddMulAlgo::Decomp4x4, AddMulAlgo::M4RM], 512, true),
+            (vec![AddMulAlgo::Decomp2x2, AddMulAlgo::Decomp2x2, AddMulAlgo::M4RM], 512, true),
+        ];
+        for (algos, recurse_sz, pad) in plans {
+            let plan = Plan { algos, recurse_sz, pad };
+            for threads in [1, 3] {
+                let mut prod = init.clone();
+                unsafe { addmul_planned::<128, 16, _, _, _>(&mut prod, &m1, &m2, &plan, threads) };
+                assert!(prod == tgt);
+            }
+        }
+    }
+
+    #[test]
+    fn test_profile() {
+        use crate::mul::addmul_with_profile;
+        let path = std::env::temp_dir().join(format!("gf2mul_profile_{}.json", std::process::id()));
+        let profile = TuningProfile { strassen_cutoff: 1024, window_align: 32, decomp_order: vec![AddMulAlgo::Decomp4x4] };
+        profile.save(&path).unwrap();
+        assert!(TuningProfile::load(&path).unwrap() == profile);
+        std::fs::write(&path, r#"{"strassen_cutoff": 4096, "window_align": 24, "decomp_order": []}"#).unwrap();
+        assert!(matches!(TuningProfile::load(&path), Err(ProfileError::WindowAlign(24, _))));
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(TuningProfile::load(&path), Err(ProfileError::Io(_))));
+        let invalid = [
+            TuningProfile { strassen_cutoff: 512, window_align: 64, ..TuningProfile::default() },
+            TuningProfile { decomp_order: vec![AddMulAlgo::Decomp2x2, AddMulAlgo::Decomp2x2], ..TuningProfile::default() },
+            TuningProfile { decomp_order: vec![AddMulAlgo::M4RM], ..TuningProfile::default() },
+        ];
+        for profile in invalid {
+            assert!(profile.validate().is_err());
+        }
+
+        let mut rng = rand::thread_rng();
+        let (nrows, conn, ncols) = (rng.gen_range(1000..1300), rng.gen_range(1000..1300), rng.gen_range(1000..1300));
+        let m1 = GF2Mat::random(nrows, conn, &mut rng);
+        let m2 = GF2Mat::random(conn, ncols, &mut rng);
+        let init = GF2Mat::random(nrows, ncols, &mut rng);
+        let tgt = &init + &naive_mul(&m1, &m2);
+        let profiles = [
+            TuningProfile { strassen_cutoff: 512, window_align: 32, decomp_order: vec![AddMulAlgo::Decomp2x2] },
+            TuningProfile { strassen_cutoff: 1024, window_align: 64, decomp_order: vec![AddMulAlgo::Decomp2x2] },
+            TuningProfile { strassen_cutoff: 256, window_align: 16, decomp_order: vec![AddMulAlgo::Decomp4x4, AddMulAlgo::Decomp2x2] },
+            TuningProfile { strassen_cutoff: 512, window_align: 16, decomp_order: vec![] },
+        ];
+        for profile in profiles {
+            profile.validate().unwrap();
+            for threads in [1, 3] {
+                let mut prod = init.clone();
+                unsafe { addmul_with_profile(&mut prod, &m1, &m2, threads, &profile) };
+                assert!(prod == tgt);
+            }
+        }
+
+        let opts = TuneOptions {
+            sizes: vec![600],
+            reps: 1,
+            cutoffs: vec![256, 512],
+            orders: vec![vec![AddMulAlgo::Decomp4x4]],
+            ..TuneOptions::default()
+        };
+        TuningProfile::autotune(&opts).validate().unwrap();
+    }
+
+    // these go through a slot of their own, as other tests use the global
+    // profile concurrently
+    #[test]
+    fn test_profile_install() {
+        use std::sync::{Arc, RwLock};
+        use crate::mul::addmul_with_profile;
+        let slot = RwLock::new(Arc::new(TuningProfile::default()));
+        let profile = TuningProfile { strassen_cutoff: 512, window_align: 32, decomp_order: vec![AddMulAlgo::Decomp2x2] };
+        profile.clone().install_in(&slot).unwrap();
+        assert!(**slot.read().unwrap() == profile);
+
+        let mut rng = rand::thread_rng();
+        let m1 = GF2Mat::random(1100, 1050, &mut rng);
+        let m2 = GF2Mat::random(1050, 1200, &mut rng);
+        let mut prod = GF2Mat::zero(1100, 1200);
+        let installed = slot.read().unwrap().clone();
+        unsafe { addmul_with_profile(&mut prod, &m1, &m2, 2, &installed) };
+        assert!(prod == naive_mul(&m1, &m2));
+
+        let invalid = TuningProfile { window_align: 48, ..profile.clone() };
+        assert!(invalid.install_in(&slot).is_err());
+        assert!(**slot.read().unwrap() == profile);
+    }
+
+    #[test]
+    fn test_profile_init() {
+        use std::sync::{Arc, RwLock};
+        let slot = RwLock::new(Arc::new(TuningProfile::default()));
+        let path = std::env::temp_dir().join(format!("gf2mul_init_profile_{}.json", std::process::id()));
+        let profile = TuningProfile { strassen_cutoff: 2048, window_align: 64, decomp_order: vec![AddMulAlgo::Decomp4x4] };
+        profile.save(&path).unwrap();
+        TuningProfile::init_in(&slot, Some(&path)).unwrap();
+        assert!(**slot.read().unwrap() == profile);
+
+        // a broken profile is reported, and leaves the current one alone
+        std::fs::write(&path, "{").unwrap();
+        assert!(matches!(TuningProfile::init_in(&slot, Some(&path)), Err(ProfileError::Json(_))));
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(TuningProfile::init_in(&slot, Some(&path)), Err(ProfileError::Io(_))));
+        TuningProfile::init_in(&slot, None::<&std::path::Path>).unwrap();
+        assert!(**slot.read().unwrap() == profile);
+    }
+
+    #[test]
+    fn test_ops() {
+        let mut rng = rand::thread_rng();
+        let (n, k, m) = (70, 131, 45);
+        let m1 = GF2Mat::random(n, k, &mut rng);
+        let m2 = GF2Mat::random(k, m, &mut rng);
+        assert!(&m1 * &m2 == naive_mul(&m1, &m2));
+
+        let a = GF2Mat::random(n, m, &mut rng);
+        let b = GF2Mat::random(n, m, &mut rng);
+        let sum = &a + &b;
+        let zero = GF2Mat::zero(n, m);
+        let ones = !&zero;
+        assert!(&(&a | &b) + &(&a & &b) == sum);
+        assert!(&(&a & &b) & &a == &a & &b);
+        assert!(&a & &zero == zero);
+        assert!(&a | &zero == a);
+        assert!(&a | &ones == ones);
+        assert!(&a & &(!&a) == zero);
+        assert!(&ones + &a == !&a);
+        // the padding has to stay clear for the kernels
+        assert!(&(!&(!&a)) + &a == zero);
+
+        let mut c = a.to_mat();
+        c += &b;
+        assert!(c == sum);
+        c += &b;
+        assert!(c == a);
+
+        let sq = GF2Mat::random(m, m, &mut rng);
+        let mut c = a.to_mat();
+        c *= &sq;
+        assert!(c == &a * &sq);
+
+        // windows work on either side
+        let a_window = unsafe { a.get_window_unchecked::<128>(0, 0, n, 1) };
+        assert!(&a_window + &b == sum);
+        let mut c = a.to_mat();
+        {
+            let mut c_window = unsafe { c.get_window_mut_unchecked::<128>(0, 0, n, 1) };
+            c_window += &b;
+        }
+        assert!(c == sum);
+
+        // finer windows span more chunks than their columns need
+        let a_fine = unsafe { a.get_window_unchecked::<16>(0, 0, n, 8) };
+        let sq_fine = unsafe { sq.get_window_unchecked::<16>(0, 0, m, 8) }.to_mat();
+        assert!(a_fine.to_mat() == a);
+        assert!(a_fine.rank() == a.rank());
+        assert!(&a_fine * &sq_fine == &a * &sq);
+        let mut c = a.to_mat();
+        {
+            let mut c_fine = unsafe { c.get_window_mut_unchecked::<16>(0, 0, n, 8) };
+            c_fine *= &sq_fine;
+        }
+        assert!(c == &a * &sq);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot add matrices of dimensions 3x4 and 3x5")]
+    fn test_ops_dim_mismatch() {
+        let mut a = GF2Mat::zero(3, 4);
+        a += &GF2Mat::zero(3, 5);
+    }
+
+    #[test]
+    fn test_accessors() {
+        let mut rng = rand::thread_rng();
+        let (n, m) = (77, 150);
+        let mut a = GF2Mat::random(n, m, &mut rng);
+        let ones: Vec<_> = a.iter_ones().collect();
+        let mut expected = Vec::new();
+        for i in 0..n {
+            for j in 0..m {
+                if a.get(i, j) {
+                    expected.push((i, j));
+                }
+            }
+        }
+        assert_eq!(ones, expected);
+
+        for (i, row) in a.rows().enumerate() {
+            assert_eq!(row.nrows(), 1);
+            assert_eq!(row.ncols(), m);
+            for j in 0..m {
+                assert_eq!(row.get(0, j), a.get(i, j));
+            }
+        }
+        let col = a.col(17);
+        assert_eq!((col.nrows(), col.ncols()), (n, 1));
+        for i in 0..n {
+            assert_eq!(col.get(i, 0), a.get(i, 17));
+        }
+
+        let old = a.get(5, 149);
+        a.flip(5, 149);
+        assert_eq!(a.get(5, 149), !old);
+        a.set(5, 149, true);
+        assert!(a.get(5, 149));
+        a.set(5, 149, false);
+        assert!(!a.get(5, 149));
+        assert!(matches!(a.try_get(77, 0), Err(MatAccessError::OutOfBounds(77, 0, 77, 150))));
+        assert!(a.try_set(0, 150, true).is_err());
+
+        let mut window = unsafe { a.get_window_mut_unchecked::<128>(10, 0, 5, 1) };
+        window.set(2, 3, true);
+        assert!(window.get(2, 3));
+        assert!(a.get(12, 3));
+
+        let sparse = GF2Mat::zero(300, 1000);
+        assert_eq!(sparse.iter_ones().count(), 0);
+    }
+
+    #[test]
+    fn test_views() {
+        let mut rng = rand::thread_rng();
+        let (n, m) = (50, 3000);
+        let mut a = GF2Mat::random(n, m, &mut rng);
+        for (rows, cols) in [(0..50, 0..3000), (3..40, 1024..2048), (0..50, 2048..3000), (7..9, 5..2900), (10..20, 1023..1025), (0..0, 0..0)] {
+            let view = a.view(rows.clone(), cols.clone());
+            assert_eq!(view.is_borrowed(), cols.start % 1024 == 0 && (cols.end % 1024 == 0 || cols.end == m));
+            assert_eq!((view.nrows(), view.ncols()), (rows.len(), cols.len()));
+            for (i, j) in view.iter_ones() {
+                assert!(a.get(rows.start + i, cols.start + j));
+            }
+            for i in 0..rows.len() {
+                for j in 0..cols.len() {
+                    assert_eq!(view.get(i, j), a.get(rows.start + i, cols.start + j));
+                }
+            }
+        }
+
+        // unaligned mutable views have to write their changes back
+        let orig = a.to_mat();
+        {
+            let mut view = a.view_mut(5..15, 100..1200);
+            assert!(!view.is_borrowed());
+            for i in 0..10 {
+                for j in 0..1100 {
+                    view.flip(i, j);
+                }
+            }
+        }
+        for i in 0..n {
+            for j in 0..m {
+                let inside = (5..15).contains(&i) && (100..1200).contains(&j);
+                assert_eq!(a.get(i, j), orig.get(i, j) ^ inside);
+            }
+        }
+        {
+            let mut view = a.view_mut(.., 1024..);
+            assert!(view.is_borrowed());
+            view.clear();
+        }
+        assert_eq!(a.iter_ones().filter(|&(_, j)| j >= 1024).count(), 0);
+
+        // views are ordinary matrices for the rest of the crate
+        let b = GF2Mat::random(3100, 70, &mut rng);
+        let prod = &a.view(.., 17..2017) * &b.view(1017..3017, ..);
+        for i in 0..n {
+            for j in 0..70 {
+                let dot = (17..2017).fold(false, |acc, k| acc ^ (a.get(i, k) & b.get(k - 17 + 1017, j)));
+                assert_eq!(prod.get(i, j), dot);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Range 3..51 is out of bounds for dimension of size 50")]
+    fn test_view_out_of_bounds() {
+        let a = GF2Mat::zero(50, 10);
+        a.view(3..51, ..);
+    }
+
+    #[test]
+    fn test_transpose() {
+        let mut rng = rand::thread_rng();
+        for (n, m) in [(1, 1), (1, 200), (63, 65), (200, 1), (700, 1300), (1100, 64)] {
+            let a = GF2Mat::random(n, m, &mut rng);
+            let t = a.transpose();
+            assert_eq!((t.nrows(), t.ncols()), (m, n));
+            for i in 0..n {
+                for j in 0..m {
+                    assert_eq!(t.get(j, i), a.get(i, j));
+                }
+            }
+            assert!(t.transpose() == a);
+
+            let view = a.view(1.., 1..);
+            assert!(view.transpose() == t.view(1.., 1..).to_mat());
+
+            // the target is overwritten, as when PLE swaps columns in place
+            let mut dst = GF2Mat::random(m, n, &mut rng);
+            crate::transpose::transpose_into(&mut dst, &a);
+            assert!(dst == t);
+        }
+
+        for n in [1, 64, 100, 777] {
+            let a = GF2Mat::random(n, n, &mut rng);
+            let mut t = a.to_mat();
+            t.transpose_in_place();
+            assert!(t == a.transpose());
+        }
+    }
+
+    #[test]
+    fn test_constructors() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let id = GF2Mat::identity(130);
+        assert!(id == GF2Mat::from_fn(130, 130, |i, j| i == j));
+        assert_eq!(id.iter_ones().collect::<Vec<_>>(), (0..130).map(|i| (i, i)).collect::<Vec<_>>());
+
+        let rows = [[true, false, true], [false, false, true]];
+        let a = GF2Mat::from_rows(&rows);
+        assert_eq!((a.nrows(), a.ncols()), (2, 3));
+        assert!(a == GF2Mat::from_fn(2, 3, |i, j| rows[i][j]));
+        assert!(a == GF2Mat::from_words(3, &[[0b101u64], [0b100 | 0xf0]]));
+        let wide = GF2Mat::from_words(70, &[[u64::MAX, 1u64 | 0xf00]]);
+        assert_eq!(wide.iter_ones().count(), 65);
+
+        let mut rng1 = StdRng::seed_from_u64(1234);
+        let mut rng2 = StdRng::seed_from_u64(1234);
+        assert!(GF2Mat::random(100, 100, &mut rng1) == GF2Mat::random(100, 100, &mut rng2));
+        let (n, m) = (300, 500);
+        for density in [0.0, 0.01, 0.3, 0.5, 0.9, 1.0] {
+            let a = GF2Mat::random_with(n, m, &mut rng1, density);
+            let b = GF2Mat::random_with(n, m, &mut rng2, density);
+            assert!(a == b);
+            let frac = a.iter_ones().count() as f64 / (n * m) as f64;
+            assert!((frac - density).abs() < 0.01, "density {density} gave {frac}");
+            // the padding has to stay clear for the kernels
+            assert!(&a * &GF2Mat::identity(m) == a);
+        }
+    }
+
+    #[test]
+    fn test_eq_hash() {
+        use std::collections::HashSet;
+
+        let mut rng = rand::thread_rng();
+        let a = GF2Mat::random(100, 1500, &mut rng);
+        let mut b = AlignedGF2Mat::<16>::zero(100, 1500);
+        for (i, j) in a.iter_ones() {
+            b.set(i, j, true);
+        }
+        assert!(a == b);
+        assert!(a.view(.., ..) == b);
+        assert!(a.view(.., 1024..) == b.view(.., 1024..));
+        assert!(a.view(3..7, 5..100) == b.view(3..7, 5..100));
+        b.flip(99, 1499);
+        assert!(a != b);
+        assert!(a.view(..99, ..) == b.view(..99, ..));
+        assert!(a != GF2Mat::zero(100, 1499));
+
+        let windows: Vec<_> = a.rows().collect();
+        assert!(windows[3] == a.view(3..4, ..));
+
+        assert!(!a.is_zero());
+        assert!(GF2Mat::zero(100, 1500).is_zero());
+        assert!(GF2Mat::random_with(100, 1500, &mut rng, 0.0) == GF2Mat::zero(100, 1500));
+        // tiny densities are near zero rather than flipping everything
+        assert!(GF2Mat::random_with(100, 1500, &mut rng, 1e-17).is_zero());
+        assert!(GF2Mat::random_with(100, 1500, &mut rng, 1.0 - 1e-17) == !&GF2Mat::zero(100, 1500));
+        assert!(GF2Mat::random_with(100, 1500, &mut rng, f64::MIN_POSITIVE).is_zero());
+
+        let mut set = HashSet::new();
+        for _ in 0..100 {
+            set.insert(GF2Mat::random_with(4, 4, &mut rng, 0.1));
+        }
+        for m in &set {
+            assert!(set.contains(&m.to_mat()));
+        }
+        assert!(set.len() < 100);
+    }
+
+    #[test]
+    fn test_clone_default_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<GF2Mat>();
+        assert_send_sync::<GF2MatWindow<'static, 16>>();
+        assert_send_sync::<GF2MatWindowMut<'static, 16>>();
+        assert_send_sync::<GF2MatView<'static, 16>>();
+        assert_send_sync::<GF2MatViewMut<'static, 16>>();
+
+        let empty = GF2Mat::default();
+        assert_eq!((empty.nrows(), empty.ncols()), (0, 0));
+        assert!(GF2MatWindow::<128>::default() == empty);
+        assert!(GF2MatWindowMut::<128>::default().is_zero());
+
+        let mut rng = rand::thread_rng();
+        let a = GF2Mat::random(400, 300, &mut rng);
+        let mut b = a.clone();
+        assert!(a == b);
+        b.flip(0, 0);
+        assert!(a != b);
+
+        // hand disjoint row blocks of `c` and shared windows of `a` to threads
+        let mut c = GF2Mat::zero(400, 300);
+        let blocks = unsafe { c.division_mut_unchecked::<128, 4, 1>(100, 1) };
+        std::thread::scope(|s| {
+            for (i, [mut blk]) in blocks.into_iter().enumerate() {
+                let src = unsafe { a.get_window_unchecked::<128>(i * 100, 0, 100, 1) };
+                s.spawn(move || {
+                    blk.copy_from(&src);
+                    blk.flip(0, 0);
+                });
+            }
+        });
+        for i in (0..400).step_by(100) {
+            c.flip(i, 0);
+        }
+        assert!(c == a);
+    }
+
+    #[cfg(feature = "m4ri")]
+    #[test]
+    fn test_m4ri_roundtrip() {
+        let mut rng = rand::thread_rng();
+        for &(nrows, ncols) in &[(1, 1), (3, 64), (70, 65), (129, 1000)] {
+            let bm = BinMatrix::random(nrows, ncols);
+            let a = GF2Mat::from_m4ri(&bm);
+            for i in 0..nrows {
+                for j in 0..ncols {
+                    assert_eq!(a.get(i, j), bm.bit(i, j));
+                }
+            }
+            assert!(a.to_m4ri() == bm);
+
+            // other alignments and windows convert just the same
+            let b = AlignedGF2Mat::<8>::random(nrows, ncols, &mut rng);
+            assert!(AlignedGF2Mat::<8>::from_m4ri(&b.to_m4ri()) == b);
+            let w = unsafe { a.get_window_unchecked::<128>(0, 0, nrows, a.nchunk_cols()) };
+            assert!(GF2Mat::from_m4ri(&w.to_m4ri()) == a);
+        }
+    }
+
+    #[test]
+    fn test_echelonize() {
+        let mut rng = rand::thread_rng();
+        let mut mats = vec![GF2Mat::zero(5, 7), GF2Mat::identity(9)];
+        for _ in 0..30 {
+            let (nrows, ncols) = (rng.gen_range(1..300), rng.gen_range(1..1100));
+            mats.push(GF2Mat::random(nrows, ncols, &mut rng));
+            mats.push(GF2Mat::random_with(nrows, ncols, &mut rng, 0.01));
+            // rank deficient, with pivotless columns all over the place
+            let inner = rng.gen_range(1..nrows.min(ncols) + 1);
+            let lhs = GF2Mat::random(nrows, inner, &mut rng);
+            let rhs = GF2Mat::random_with(inner, ncols, &mut rng, 0.1);
+            mats.push(naive_mul(&lhs, &rhs));
+        }
+        for a in &mats {
+            let (tgt, rank) = naive_rref(a);
+            let mut full = a.clone();
+            assert_eq!(echelonize(&mut full, true), rank);
+            assert!(full == tgt);
+
+            // the plain echelon form has leading ones moving strictly right,
+            // and spans the same rows
+            let mut ef = a.clone();
+            assert_eq!(echelonize(&mut ef, false), rank);
+            let leads: Vec<_> = ef.rows().map(|row| row.iter_ones().next().map(|(_, j)| j)).collect();
+            assert!(leads[rank..].iter().all(|l| l.is_none()));
+            assert!(leads[..rank].windows(2).all(|w| w[0] < w[1]));
+            assert!(naive_rref(&ef).0 == tgt);
+        }
+
+        // works on windows in place, leaving the rest of the matrix alone
+        let a = GF2Mat::random(200, 2000, &mut rng);
+        let mut b = a.clone();
+        let rank = {
+            let mut w = unsafe { b.get_window_mut_unchecked::<128>(0, 1, 100, 1) };
+            echelonize(&mut w, true)
+        };
+        let (tgt, tgt_rank) = naive_rref(&a.view(..100, 1024..2000).to_mat());
+        assert_eq!(rank, tgt_rank);
+        assert!(b.view(..100, 1024..) == tgt);
+        assert!(b.view(100.., ..) == a.view(100.., ..));
+        assert!(b.view(.., ..1024) == a.view(.., ..1024));
+    }
+
+    #[test]
+    fn test_rank() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..20 {
+            let (nrows, ncols) = (rng.gen_range(1..200), rng.gen_range(1..200));
+            let density = rng.gen_range(0.0..0.2);
+            let a = GF2Mat::random_with(nrows, ncols, &mut rng, density);
+            assert_eq!(a.rank(), naive_rref(&a).1);
+            let w = unsafe { a.get_window_unchecked::<16>(0, 0, nrows, ncols.div_ceil(128)) };
+            assert_eq!(w.rank(), naive_rref(&a).1);
+        }
+
+        // big enough for the block decomposition, with rank deficiencies and
+        // empty columns in both halves
+        let low_rank = naive_mul(
+            &GF2Mat::random(2500, 1800, &mut rng),
+            &GF2Mat::random(1800, 4500, &mut rng),
+        );
+        let mut sparse = GF2Mat::random_with(3000, 5000, &mut rng, 0.0005);
+        for j in (0..5000).step_by(7) {
+            sparse.set(j % 3000, j, true);
+        }
+        let mats = [
+            GF2Mat::random(3000, 3000, &mut rng),
+            GF2Mat::random(2000, 6000, &mut rng),
+            GF2Mat::random(6000, 2500, &mut rng),
+            low_rank,
+            sparse,
+        ];
+        for a in &mats {
+            let mut ef = a.clone();
+            let rank = echelonize(&mut ef, false);
+            assert_eq!(a.rank(), rank);
+
+            // the decomposition has to reproduce `a`
+            let mut d = a.clone();
+            let perms = ple(&mut d);
+            let (perm, pivots) = (&perms.rows, &perms.rank_profile);
+            assert_eq!(perms.rank(), rank);
+            let l = GF2Mat::from_fn(a.nrows(), rank, |i, t| i == t || (i > t && d.get(i, pivots[t])));
+            let e = GF2Mat::from_fn(rank, a.ncols(), |t, j| j >= pivots[t] && d.get(t, j));
+            assert!((0..rank).all(|t| e.get(t, pivots[t])));
+            let mut prod = mul(&l, &e).unwrap();
+            for (i, &p) in perm.iter().enumerate().rev() {
+                prod.swap_rows(i, p);
+            }
+            assert!(prod == *a);
+        }
+    }
+
+    #[test]
+    fn test_pluq() {
+        let mut rng = rand::thread_rng();
+        let low_rank = mul(&GF2Mat::random(1000, 700, &mut rng), &GF2Mat::random(700, 6000, &mut rng)).unwrap();
+        let mats = [
+            GF2Mat::random(1, 1, &mut rng),
+            GF2Mat::random(300, 500, &mut rng),
+            GF2Mat::random(5000, 4700, &mut rng),
+            // every pivot column is shifted, so that most columns get swapped
+            GF2Mat::from_fn(400, 600, |_, j| j >= 50 && rng.gen()),
+            low_rank,
+        ];
+        for a in &mats {
+            let (nrows, ncols) = (a.nrows(), a.ncols());
+            // decompose a window in the middle of a larger matrix, nothing
+            // around it may change
+            let mut big = GF2Mat::random(nrows + 20, ncols + 2048, &mut rng);
+            let before = big.clone();
+            let mut w = unsafe { big.get_window_mut_unchecked::<128>(10, 1, nrows, a.nchunk_cols()) };
+            w.copy_from(a);
+            let perms = pluq(&mut w);
+            let r = perms.rank();
+            assert_eq!(r, a.rank());
+            assert_eq!(perms.rank_profile.len(), r);
+
+            // the window spans whole chunks, so it is wider than `a`
+            let l = GF2Mat::from_fn(nrows, r, |i, t| i == t || (i > t && w.get(i, t)));
+            let u = GF2Mat::from_fn(r, w.ncols(), |t, j| j >= t && w.get(t, j));
+            assert!((0..r).all(|t| u.get(t, t)));
+            let mut prod = mul(&l, &u).unwrap().transpose();
+            for (j, &q) in perms.cols.iter().enumerate().rev() {
+                prod.swap_rows(j, q);
+            }
+            let mut prod = prod.transpose();
+            for (i, &p) in perms.rows.iter().enumerate().rev() {
+                prod.swap_rows(i, p);
+            }
+            assert!(prod.view(.., ..ncols).mat_eq(a));
+            assert!(prod.view(.., ncols..).is_zero());
+            for i in (0..10).chain(nrows + 10..nrows + 20) {
+                assert!(big.row(i) == before.row(i));
+            }
+            assert!(big.view(.., ..1024) == before.view(.., ..1024));
+        }
+    }
+
+    #[test]
+    fn test_solve() {
+        let mut rng = rand::thread_rng();
+        let shapes = [(1, 1, 1), (50, 50, 3), (300, 120, 40), (120, 300, 1), (2500, 5000, 70), (5000, 4500, 200)];
+        for (m, n, k) in shapes {
+            // consistent by construction, possibly with many solutions
+            let a = GF2Mat::random(m, n, &mut rng);
+            let b = mul(&a, &GF2Mat::random(n, k, &mut rng)).unwrap();
+            let x = solve(&a, &b).unwrap();
+            assert!(mul(&a, &x).unwrap() == b);
+
+            let b = mul(&GF2Mat::random(k, m, &mut rng), &a).unwrap();
+            let x = solve_left(&a, &b).unwrap();
+            assert!(mul(&x, &a).unwrap() == b);
+        }
+        // a random right hand side of a tall system is hopeless
+        let a = GF2Mat::random(300, 100, &mut rng);
+        let b = GF2Mat::random(300, 5, &mut rng);
+        assert!(matches!(solve(&a, &b), Err(SolveError::Inconsistent)));
+        assert!(matches!(solve_left(&a.transpose(), &b.transpose()), Err(SolveError::Inconsistent)));
+        // as is any non-zero one for the zero matrix
+        let mut b = GF2Mat::zero(10, 2);
+        assert!(solve(&GF2Mat::zero(10, 7), &b).unwrap().is_zero());
+        b.set(9, 1, true);
+        assert!(matches!(solve(&GF2Mat::zero(10, 7), &b), Err(SolveError::Inconsistent)));
+        assert!(matches!(solve(&a, &GF2Mat::zero(299, 5)), Err(SolveError::DimMismatch(300, 100, 299, 5))));
+    }
+
+    #[test]
+    fn test_kernel() {
+        let mut rng = rand::thread_rng();
+        let low_rank = mul(&GF2Mat::random(700, 300, &mut rng), &GF2Mat::random(300, 900, &mut rng)).unwrap();
+        let mats = [
+            GF2Mat::zero(5, 8),
+            GF2Mat::identity(70),
+            GF2Mat::random(1, 1, &mut rng),
+            GF2Mat::random(200, 230, &mut rng),
+            GF2Mat::random(2000, 2100, &mut rng),
+            GF2Mat::random(600, 5000, &mut rng),
+            low_rank,
+        ];
+        for a in &mats {
+            let rank = a.rank();
+            for (ker, dim) in [(a.right_kernel().transpose(), a.ncols()), (a.left_kernel().transpose(), a.nrows())] {
+                // `ker` holds the basis in its columns here
+                assert_eq!(ker.nrows(), dim);
+                assert_eq!(ker.ncols(), dim - rank);
+                assert_eq!(ker.rank(), dim - rank);
+            }
+            let ker = a.right_kernel();
+            if ker.nrows() != 0 {
+                assert!(mul(a, &ker.transpose()).unwrap().is_zero());
+            }
+            let mut rref = ker.clone();
+            echelonize(&mut rref, true);
+            assert!(rref == ker);
+
+            let ker = a.left_kernel();
+            if ker.nrows() != 0 {
+                assert!(mul(&ker, a).unwrap().is_zero());
+            }
+        }
+    }
+
+    #[test]
+    fn test_trsm() {
+        let mut rng = rand::thread_rng();
+        for r in [1, 5, 8, 9, 100, 1500, 2500] {
+            let k = rng.gen_range(1..300);
+            // for a unit diagonal everything outside the triangle is ignored,
+            // so `full` may hold anything there
+            let full = GF2Mat::random(r, r, &mut rng);
+            let upper = GF2Mat::from_fn(r, r, |i, j| i == j || (i < j && full.get(i, j)));
+            let lower = GF2Mat::from_fn(r, r, |i, j| i == j || (i > j && full.get(i, j)));
+
+            let b = GF2Mat::random(r, k, &mut rng);
+            let mut x = b.clone();
+            trsm_upper_left(&full, &mut x, Diag::Unit).unwrap();
+            assert!(mul(&upper, &x).unwrap() == b);
+            let mut x = b.clone();
+            trsm_lower_left(&lower, &mut x, Diag::NonUnit).unwrap();
+            assert!(mul(&lower, &x).unwrap() == b);
+
+            let b = GF2Mat::random(k, r, &mut rng);
+            let mut x = b.clone();
+            trsm_upper_right(&upper, &mut x, Diag::NonUnit).unwrap();
+            assert!(mul(&x, &upper).unwrap() == b);
+            let mut x = b.clone();
+            trsm_lower_right(&full, &mut x, Diag::Unit).unwrap();
+            assert!(mul(&x, &lower).unwrap() == b);
+        }
+        let mut singular = GF2Mat::identity(10);
+        singular.set(6, 6, false);
+        let mut b = GF2Mat::zero(10, 3);
+        assert!(matches!(trsm_upper_left(&singular, &mut b, Diag::NonUnit), Err(TrsmError::Singular(6))));
+        assert!(trsm_upper_left(&singular, &mut b, Diag::Unit).is_ok());
+        assert!(matches!(trsm_lower_right(&singular, &mut b, Diag::Unit), Err(TrsmError::DimMismatch(10, 10, 3))));
+        assert!(matches!(trsm_lower_left(&GF2Mat::zero(10, 3), &mut b, Diag::Unit), Err(TrsmError::NotSquare(10, 3))));
+    }
+
+    #[test]
+    fn test_det() {
+        let mut rng = rand::thread_rng();
+        assert!(GF2Mat::zero(0, 0).det());
+        assert!(GF2Mat::identity(3000).det());
+        for n in [1, 2, 10, 64, 65, 300, 5000] {
+            for _ in 0..3 {
+                let a = GF2Mat::random(n, n, &mut rng);
+                assert_eq!(a.det(), a.rank() == n);
+                assert_eq!(a.is_invertible(), a.det());
+            }
+        }
+        // singular only because of the very last column
+        let n = 5000;
+        let mut a = GF2Mat::from_fn(n, n, |i, j| i == j || (i < j && rng.gen()));
+        for i in 0..n {
+            a.set(i, n - 1, a.get(i, 0) ^ a.get(i, 7));
+        }
+        assert!(!a.det());
+        assert!(!a.view(1.., 1..).is_invertible());
+        assert!(!GF2Mat::identity(3).view(..2, ..).is_invertible());
+    }
+
+    #[test]
+    fn test_inverse() {
+        let mut rng = rand::thread_rng();
+        for n in [1, 2, 7, 64, 129, 300, 1000] {
+            for _ in 0..4 {
+                let a = GF2Mat::random(n, n, &mut rng);
+                match a.inverse() {
+                    Some(inv) => assert!(mul(&a, &inv).unwrap() == GF2Mat::identity(n)),
+                    None => assert!(a.rank() < n),
+                }
+            }
+        }
+        // P * L * U with random unit triangular factors is always invertible,
+        // the larger sizes go through the PLE based path
+        for n in [2500, 4500] {
+            let l = GF2Mat::from_fn(n, n, |i, j| i == j || (i > j && rng.gen()));
+            let u = GF2Mat::from_fn(n, n, |i, j| i == j || (i < j && rng.gen()));
+            let mut a = mul(&l, &u).unwrap();
+            for i in 0..n {
+                a.swap_rows(i, rng.gen_range(i..n));
+            }
+            let mut inv = a.clone();
+            inv.invert().unwrap();
+            assert!(mul(&a, &inv).unwrap() == GF2Mat::identity(n));
+            assert!(mul(&inv, &a).unwrap() == GF2Mat::identity(n));
+
+            // repeating a row makes it singular, which must leave it untouched
+            let mut sing = a.clone();
+            let row = a.row(0).to_mat();
+            sing.view_mut(n - 1..n, ..).copy_from(&row);
+            let copy = sing.clone();
+            assert!(matches!(sing.invert(), Err(MatInvError::Singular(m)) if m == n));
+            assert!(sing == copy);
+        }
+        assert!(matches!(GF2Mat::zero(3, 4).invert(), Err(MatInvError::NotSquare(3, 4))));
+        assert!(GF2Mat::zero(0, 0).inverse().unwrap().nrows() == 0);
+    }
+
+    #[test]
+    fn test_pow() {
+        let mut rng = rand::thread_rng();
+        for n in [1, 5, 64, 130, 700] {
+            let a = GF2Mat::random(n, n, &mut rng);
+            assert!(a.pow(0) == GF2Mat::identity(n));
+            assert!(a.pow(1) == a);
+            let mut b = GF2Mat::identity(n);
+            for e in 1..=13u128 {
+                b = mul(&b, &a).unwrap();
+                assert!(a.pow(e) == b);
+            }
+            // 2^100 + 5 as a u128 and as bytes with leading zeros
+            let e = (1u128 << 100) + 5;
+            let p = a.pow(e);
+            assert!(p == a.pow(&e.to_be_bytes()[..]));
+            let mut bytes = [0u8; 20];
+            bytes[4..].copy_from_slice(&e.to_be_bytes());
+            assert!(p == a.pow(bytes));
+            assert!(p == mul(&a.pow(1u128 << 100), &a.pow(5)).unwrap());
+        }
+        // nilpotent, strictly upper triangular
+        let a = GF2Mat::from_fn(100, 100, |i, j| i < j);
+        assert!(a.pow(99).get(0, 99));
+        assert!(a.pow(100).is_zero());
+        assert!(a.pow([0xff; 40]).is_zero());
+        assert!(GF2Mat::zero(0, 0).pow(3) == GF2Mat::zero(0, 0));
+    }
+
+    #[test]
+    fn test_charpoly() {
+        let mut rng = rand::thread_rng();
+        // the first k with I, A, ..., A^k dependent, by brute force
+        let naive_minpoly_degree = |a: &GF2Mat| {
+            let n = a.nrows();
+            let mut pows = vec![GF2Mat::identity(n)];
+            loop {
+                let flat = GF2Mat::from_fn(pows.len(), n * n, |k, ij| pows[k].get(ij / n, ij % n));
+                if flat.rank() < pows.len() {
+                    return pows.len() - 1;
+                }
+                pows.push(mul(pows.last().unwrap(), a).unwrap());
+            }
+        };
+        for n in [1, 2, 5, 13, 64, 100, 300] {
+            let a = GF2Mat::random(n, n, &mut rng);
+            let (cp, mp) = (a.charpoly(), a.minpoly());
+            assert_eq!(cp.degree(), Some(n));
+            assert!(cp.eval(&a).is_zero());
+            assert!(mp.eval(&a).is_zero());
+            assert!(cp.div_rem(&mp).1.is_zero());
+            assert_eq!(cp, a.transpose().charpoly());
+            if n <= 13 {
+                assert_eq!(mp.degree(), Some(naive_minpoly_degree(&a)));
+            }
+        }
+
+        // two companion blocks of x^3 + x + 1 and an identity, in a random basis
+        let p = GF2Poly::from_coeffs(&[true, true, false, true]);
+        let x1 = GF2Poly::from_coeffs(&[true, true]);
+        let n = 10;
+        let mut a = GF2Mat::zero(n, n);
+        for s in [0, 3] {
+            a.set(s, s + 1, true);
+            a.set(s + 1, s + 2, true);
+            a.set(s + 2, s, true);
+            a.set(s + 2, s + 1, true);
+        }
+        for i in 6..n {
+            a.set(i, i, true);
+        }
+        let t = loop {
+            let t = GF2Mat::random(n, n, &mut rng);
+            if t.is_invertible() {
+                break t;
+            }
+        };
+        let b = mul(&mul(&t, &a).unwrap(), &t.inverse().unwrap()).unwrap();
+        let expected = [&p, &p, &x1, &x1, &x1, &x1].into_iter().fold(GF2Poly::one(), |acc, f| &acc * f);
+        assert_eq!(b.charpoly(), expected);
+        assert_eq!(b.minpoly(), &p * &x1);
+        assert_eq!(b.minpoly().degree(), Some(naive_minpoly_degree(&b)));
+        assert_eq!(format!("{}", b.minpoly()), "x^4 + x^3 + x^2 + 1");
+
+        assert!(GF2Mat::identity(500).minpoly() == x1);
+        assert_eq!(GF2Mat::zero(500, 500).charpoly(), GF2Poly::monomial(500));
+        assert!(GF2Mat::zero(0, 0).minpoly().is_one());
+        let a = GF2Mat::random(3000, 3000, &mut rng);
+        assert_eq!(a.charpoly(), a.transpose().charpoly());
+    }
+
+    #[test]
+    fn test_frobenius() {
+        let mut rng = rand::thread_rng();
+        let random_invertible = |n: usize, rng: &mut rand::rngs::ThreadRng| loop {
+            let t = GF2Mat::random(n, n, rng);
+            if t.is_invertible() {
+                break t;
+            }
+        };
+        let x1 = GF2Poly::from_coeffs(&[true, true]);
+        let x2 = GF2Poly::from_coeffs(&[true, true, true]);
+        // companion blocks of x^2 + x + 1, (x^2 + x + 1)^2, x + 1 and two scalar ones
+        let mut a = GF2Mat::zero(11, 11);
+        for (i, j) in [(0, 1), (1, 0), (1, 1), (2, 3), (3, 4), (4, 5), (5, 2), (5, 4)] {
+            a.set(i, j, true);
+        }
+        for i in [6, 7, 8] {
+            a.set(i, i, true);
+        }
+        a.set(9, 10, true);
+        a.set(10, 9, true);
+        a.set(10, 10, true);
+        let t = random_invertible(11, &mut rng);
+        let b = mul(&mul(&t, &a).unwrap(), &t.inverse().unwrap()).unwrap();
+        let frob = b.frobenius_form();
+        assert_eq!(frob.invariant_factors, [&x1 * &x2, &x1 * &x2, &(&x1 * &x2) * &x2]);
+        assert!(mul(&frob.transform, &b).unwrap() == mul(&frob.form, &frob.transform).unwrap());
+        assert!(frob.transform.is_invertible());
+        assert!(frob.form == a.frobenius_form().form);
+        assert!(is_similar(&a, &b));
+
+        for n in [1, 2, 9, 64, 200] {
+            for d in [0.02, 0.5] {
+                let a = GF2Mat::random_with(n, n, &mut rng, d);
+                let frob = a.frobenius_form();
+                assert!(mul(&frob.transform, &a).unwrap() == mul(&frob.form, &frob.transform).unwrap());
+                assert!(frob.transform.is_invertible());
+                for w in frob.invariant_factors.windows(2) {
+                    assert!(w[1].div_rem(&w[0]).1.is_zero());
+                }
+                assert_eq!(frob.invariant_factors.last(), Some(&a.minpoly()));
+                let t = random_invertible(n, &mut rng);
+                let b = mul(&mul(&t, &a).unwrap(), &t.inverse().unwrap()).unwrap();
+                assert!(is_similar(&a, &b));
+                assert!(b.frobenius_form().form == frob.form);
+            }
+        }
+        // same characteristic polynomial, but not similar
+        assert!(!is_similar(&GF2Mat::identity(2), &GF2Mat::from_rows(&[[true, true], [false, true]])));
+        assert!(!is_similar(&GF2Mat::identity(2), &GF2Mat::identity(3)));
+        assert_eq!(GF2Mat::identity(5).frobenius_form().invariant_factors, vec![x1; 5]);
+        assert!(GF2Mat::zero(0, 0).frobenius_form().invariant_factors.is_empty());
+    }
+}
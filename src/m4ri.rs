@@ -0,0 +1,61 @@
+/*!
+Conversions to and from m4ri-rust's `BinMatrix`, behind the `m4ri` feature.
+
+m4ri stores row `i` as a run of 64-bit words with column `j` in bit `j % 64`
+of word `j / 64`, which is exactly our layout read as little-endian words,
+so `to_m4ri` fills a fresh `Mzd` a word at a time before handing it over.
+`BinMatrix` keeps its `Mzd` private though, so `from_m4ri` has to go through
+its public `bit` accessor.
+*/
+
+use m4ri_rust::ffi::{mzd_init, mzd_row, Rci};
+use m4ri_rust::friendly::BinMatrix;
+
+use crate::gf2mat::*;
+
+const WORD_SZ: usize = u64::BITS as usize;
+const WORD_BYTES: usize = WORD_SZ / u8::BITS as usize;
+
+fn word_range(row: &[u8], word: usize) -> std::ops::Range<usize> {
+    let start = word * WORD_BYTES;
+    start..(start + WORD_BYTES).min(row.len())
+}
+
+impl<const ALIGN: usize> AlignedGF2Mat<ALIGN> {
+    /// Copies an m4ri matrix, a bit at a time
+    pub fn from_m4ri(bm: &BinMatrix) -> Self {
+        let (nrows, ncols) = (bm.nrows(), bm.ncols());
+        let mut res = Self::zero(nrows, ncols);
+        for i in 0..nrows {
+            for j in 0..ncols {
+                if bm.bit(i, j) {
+                    unsafe { res.flip_unchecked(i, j) };
+                }
+            }
+        }
+        res
+    }
+}
+
+/// See `GF2MatLike::to_m4ri`
+pub(crate) fn to_m4ri<const ALIGN: usize, S>(mat: &S) -> BinMatrix
+where
+    S: GF2MatLike<ALIGN> + ?Sized,
+{
+    let (nrows, ncols) = (mat.nrows(), mat.ncols());
+    assert!(
+        nrows != 0 && ncols != 0,
+        "m4ri cannot represent a {nrows}x{ncols} matrix"
+    );
+    let nwords = ncols.div_ceil(WORD_SZ);
+    let mzd = unsafe { mzd_init(nrows as Rci, ncols as Rci) };
+    for i in 0..nrows {
+        let src = unsafe { mat.row_slice_unchecked(i) };
+        let dst = unsafe { std::slice::from_raw_parts_mut(mzd_row(mzd, i as Rci), nwords) };
+        for (w, word) in dst.iter_mut().enumerate() {
+            let bits = (ncols - w * WORD_SZ).min(WORD_SZ);
+            *word = load_word(&src[word_range(src, w)]) & low_mask(bits);
+        }
+    }
+    BinMatrix::from_mzd(mzd)
+}
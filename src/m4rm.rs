@@ -1,3 +1,5 @@
+use std::sync::{Barrier, RwLock};
+
 use crate::gf2mat::*;
 use const_for::const_for;
 
@@ -17,7 +19,7 @@ const fn gray_decode(mut num: usize) -> usize {
     p
 }
 
-const MAX_TBL_SZ: usize = 8;
+pub(crate) const MAX_TBL_SZ: usize = 8;
 const MAX_CODE_LEN: usize = 1 << MAX_TBL_SZ;
 type GrayTableT = u8;
 
@@ -29,7 +31,7 @@ const _: () = const {
 };
 
 // mapping from number to it's index in the gray code
-const GRAY_TBL: [GrayTableT; MAX_CODE_LEN] = const {
+pub(crate) const GRAY_TBL: [GrayTableT; MAX_CODE_LEN] = const {
     let mut tbl: [GrayTableT; MAX_CODE_LEN] = [0; MAX_CODE_LEN];
     const_for!(i in 0..MAX_CODE_LEN => {
         tbl[gray_encode(i)] = i as GrayTableT;
@@ -49,6 +51,15 @@ pub const INC_TBL: [GrayTableT; MAX_CODE_LEN] = const {
     tbl
 };
 
+// here we use the fixed table size of 8, changing this may (will) break things
+const TBL_SZ: usize = 8;
+const _: () = const { assert!(TBL_SZ <= MAX_TBL_SZ); };
+
+/// Fewest rows of `tgt` worth handing to a thread of their own
+const PAR_MIN_ROWS: usize = 256;
+/// Bytes of tables built at once by [`addmul_m4rm_par`]
+const PAR_TBL_BYTES: usize = 1 << 22;
+
 pub unsafe fn tabulate_m4rm<const ALIGN: usize, T, S>(
     tbl: &mut T,
     src_mat: &S,
@@ -83,10 +94,6 @@ where T: GF2MatLikeMut<ALIGN>,
     debug_assert!(lhs.max_ncols() >= rhs.nrows());
     debug_assert!(tgt.nrows() != 0 && tgt.nchunk_cols() != 0 && lhs.nchunk_cols() != 0); // TODO
 
-    // here we use the fixed table size of 8, changing this may (will) break things
-    const TBL_SZ: usize = 8;
-    const _: () = const { assert!(TBL_SZ <= MAX_TBL_SZ); };
-
     let mut tbl: AlignedGF2Mat<ALIGN> = AlignedGF2Mat::zero(1<<TBL_SZ, rhs.max_ncols());
     for slice_start in (0..rhs.nrows()).step_by(TBL_SZ) {
         let sub_tbl_sz = (rhs.nrows() - slice_start).min(TBL_SZ);
@@ -97,4 +104,68 @@ where T: GF2MatLikeMut<ALIGN>,
             unsafe { tgt.add_row_from_mat(&tbl, tbl_row, i); }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Same as [`addmul_m4rm`] on up to `threads` threads. The tables are built
+/// once, a batch at a time, and every thread adds them to its own range of
+/// rows of `tgt`. The threads are spawned once per call.
+///
+/// # Safety
+/// Same as [`addmul_m4rm`]: `tgt` must have as many rows as `lhs` and the
+/// same padded width as `rhs`, and `lhs` must be at least as wide as `rhs`
+/// is tall. None of the dimensions may be zero.
+pub unsafe fn addmul_m4rm_par<const ALIGN: usize, T, S1, S2>(tgt: &mut T, lhs: &S1, rhs: &S2, threads: usize)
+where T: GF2MatLikeMut<ALIGN>,
+    S1: GF2MatLike<ALIGN>,
+    S2: GF2MatLike<ALIGN> {
+    let workers = threads.min(tgt.nrows() / PAR_MIN_ROWS);
+    if workers <= 1 {
+        unsafe { addmul_m4rm(tgt, lhs, rhs) };
+        return;
+    }
+    debug_assert!(tgt.nrows() == lhs.nrows());
+    debug_assert!(tgt.nchunk_cols() == rhs.nchunk_cols());
+    debug_assert!(lhs.max_ncols() >= rhs.nrows());
+    debug_assert!(rhs.nrows() != 0 && tgt.nchunk_cols() != 0);
+
+    // windows can be shared between threads, whatever `S1` and `S2` are
+    let lhs = unsafe { lhs.get_window_unchecked::<ALIGN>(0, 0, lhs.nrows(), lhs.nchunk_cols()) };
+    let rhs = unsafe { rhs.get_window_unchecked::<ALIGN>(0, 0, rhs.nrows(), rhs.nchunk_cols()) };
+    let rows_per = tgt.nrows().div_ceil(workers);
+    let mut tgt_blks = unsafe { tgt.row_blocks_mut_unchecked(rows_per) };
+
+    let slices: Vec<usize> = (0..rhs.nrows()).step_by(TBL_SZ).collect();
+    let batch = (PAR_TBL_BYTES / ((1 << TBL_SZ) * rhs.nbyte_cols())).clamp(1, slices.len());
+    let tbls: Vec<RwLock<AlignedGF2Mat<ALIGN>>> =
+        (0..batch).map(|_| RwLock::new(AlignedGF2Mat::zero(1 << TBL_SZ, rhs.max_ncols()))).collect();
+    // the workers are spawned once and go through the batches in lockstep,
+    // building their share of a batch's tables and then all adding them
+    let nworkers = tgt_blks.len();
+    let barrier = Barrier::new(nworkers);
+    std::thread::scope(|s| {
+        for (b, blk) in tgt_blks.iter_mut().enumerate() {
+            let (tbls, barrier, slices) = (&tbls, &barrier, &slices);
+            s.spawn(move || {
+                let first = b * rows_per;
+                for starts in slices.chunks(batch) {
+                    let per = starts.len().div_ceil(nworkers);
+                    for (t, &start) in starts.iter().enumerate().skip(b * per).take(per) {
+                        let sub_tbl_sz = (rhs.nrows() - start).min(TBL_SZ);
+                        unsafe { tabulate_m4rm(&mut *tbls[t].write().unwrap(), &rhs, start, sub_tbl_sz) };
+                    }
+                    barrier.wait();
+                    for (tbl, &start) in tbls.iter().zip(starts) {
+                        let tbl = tbl.read().unwrap();
+                        for i in 0..blk.nrows() {
+                            let num = unsafe { lhs.row_slice_unchecked(first + i)[start / u8::BITS as usize] };
+                            let tbl_row = GRAY_TBL[num as usize] as usize;
+                            unsafe { blk.add_row_from_mat(&*tbl, tbl_row, i) };
+                        }
+                    }
+                    // the tables are only rebuilt once everyone is done with them
+                    barrier.wait();
+                }
+            });
+        }
+    });
+}
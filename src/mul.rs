@@ -1,19 +1,25 @@
-use crate::gf2mat::{GF2MatLike, GF2MatLikeMut};
+use crate::gf2mat::{AlignedGF2Mat, GF2MatLike, GF2MatLikeMut, MatMulError};
+use crate::plan::{plan_addmul, Plan};
+use crate::tune::TuningProfile;
 
-use crate::m4rm::addmul_m4rm;
-use crate::decomp2x2::addmul_decomp2x2;
-//use crate::decomp3x3::addmul_decomp3x3;
-use crate::decomp4x4::addmul_decomp4x4;
-//use crate::decomp5x5::addmul_decomp5x5;
+use crate::m4rm::{addmul_m4rm, addmul_m4rm_par};
+use crate::decomp2x2::{addmul_decomp2x2, addmul_decomp2x2_par};
+#[cfg(feature = "decomp3x3")]
+use crate::decomp3x3::{addmul_decomp3x3, addmul_decomp3x3_par};
+use crate::decomp4x4::{addmul_decomp4x4, addmul_decomp4x4_par};
+#[cfg(feature = "decomp5x5")]
+use crate::decomp5x5::{addmul_decomp5x5, addmul_decomp5x5_par};
 
-const STRASSEN_CUTOFF: usize = 4096;
-const WINDOW_ALIGN: usize = 16;
+/// The defaults of a [`TuningProfile`]. The routines built on `addmul` cut
+/// their own windows on `WINDOW_ALIGN`, whatever the profile.
+pub(crate) const STRASSEN_CUTOFF: usize = 4096;
+pub(crate) const WINDOW_ALIGN: usize = 16;
 const U8SZ: usize = u8::BITS as usize;
 const _ : () = const {
     assert!(WINDOW_ALIGN*U8SZ*2 <= STRASSEN_CUTOFF);
 };
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum AddMulAlgo {
     M4RM,
     Decomp2x2,
@@ -30,9 +36,167 @@ impl AddMulAlgo {
             _ => false
         }
     }
+
+    /// Whether the algorithm is built, the 3x3 and 5x5 decompositions need
+    /// the features of the same name
+    pub(crate) fn is_compiled(&self) -> bool {
+        (*self != AddMulAlgo::Decomp3x3 || cfg!(feature = "decomp3x3"))
+            && (*self != AddMulAlgo::Decomp5x5 || cfg!(feature = "decomp5x5"))
+    }
+
+    /// The number of blocks each dimension is divided into
+    pub(crate) fn dim(&self) -> usize {
+        match self {
+            AddMulAlgo::M4RM => 1,
+            AddMulAlgo::Decomp2x2 => 2,
+            AddMulAlgo::Decomp3x3 => 3,
+            AddMulAlgo::Decomp4x4 => 4,
+            AddMulAlgo::Decomp5x5 => 5,
+        }
+    }
+
+    /// The number of block products
+    pub(crate) fn nproducts(&self) -> usize {
+        match self {
+            AddMulAlgo::M4RM => 1,
+            AddMulAlgo::Decomp2x2 => 7,
+            AddMulAlgo::Decomp3x3 => 23,
+            AddMulAlgo::Decomp4x4 => 47,
+            AddMulAlgo::Decomp5x5 => 96,
+        }
+    }
+
+    /// The passes over blocks the shape of `A`, `B` and `C` it makes,
+    /// counting additions and clears of the temporaries and additions to `C`
+    pub(crate) fn block_passes(&self) -> (usize, usize, usize) {
+        match self {
+            AddMulAlgo::M4RM => (0, 0, 0),
+            AddMulAlgo::Decomp2x2 => (13, 13, 20),
+            AddMulAlgo::Decomp3x3 => (47, 53, 71),
+            AddMulAlgo::Decomp4x4 => (120, 120, 200),
+            AddMulAlgo::Decomp5x5 => (281, 251, 441),
+        }
+    }
+}
+
+/// Checks that `tgt += lhs * rhs` is well-formed. This compares the
+/// logical dimensions, two matrices whose padded widths happen to agree
+/// are still rejected if their `ncols` don't.
+fn check_addmul_dims<const ALIGN: usize, T, S1, S2>(tgt: &T, lhs: &S1, rhs: &S2) -> Result<(), MatMulError>
+where T: GF2MatLike<ALIGN>,
+    S1: GF2MatLike<ALIGN>,
+    S2: GF2MatLike<ALIGN> {
+    if lhs.ncols() != rhs.nrows() {
+        return Err(MatMulError::IncompatibleMatrices(lhs.nrows(), lhs.ncols(), rhs.nrows(), rhs.ncols()));
+    }
+    if tgt.nrows() != lhs.nrows() || tgt.ncols() != rhs.ncols() || tgt.nchunk_cols() != rhs.nchunk_cols() {
+        return Err(MatMulError::DimMismatch(lhs.nrows(), rhs.ncols(), tgt.nrows(), tgt.ncols()));
+    }
+    Ok(())
+}
+
+/// Safe version of [`addmul`], computes `tgt += lhs * rhs` after
+/// validating the dimensions of all three matrices.
+pub fn addmul_checked<const ALIGN: usize, T, S1, S2>(tgt: &mut T, lhs: &S1, rhs: &S2) -> Result<(), MatMulError>
+where T: GF2MatLikeMut<ALIGN>,
+    S1: GF2MatLike<ALIGN>,
+    S2: GF2MatLike<ALIGN> {
+    check_addmul_dims(tgt, lhs, rhs)?;
+    // the kernels assume non-empty matrices, and there is nothing to add anyway
+    if lhs.nrows() == 0 || rhs.nrows() == 0 || rhs.ncols() == 0 {
+        return Ok(());
+    }
+    unsafe { addmul(tgt, lhs, rhs) };
+    Ok(())
+}
+
+/// Computes `tgt = lhs * rhs`, overwriting the previous contents of `tgt`.
+pub fn mul_into<const ALIGN: usize, T, S1, S2>(tgt: &mut T, lhs: &S1, rhs: &S2) -> Result<(), MatMulError>
+where T: GF2MatLikeMut<ALIGN>,
+    S1: GF2MatLike<ALIGN>,
+    S2: GF2MatLike<ALIGN> {
+    check_addmul_dims(tgt, lhs, rhs)?;
+    tgt.clear();
+    addmul_checked(tgt, lhs, rhs)
+}
+
+/// Computes `lhs * rhs` into a freshly allocated matrix.
+pub fn mul<const ALIGN: usize, S1, S2>(lhs: &S1, rhs: &S2) -> Result<AlignedGF2Mat<ALIGN>, MatMulError>
+where S1: GF2MatLike<ALIGN>,
+    S2: GF2MatLike<ALIGN> {
+    if lhs.ncols() != rhs.nrows() {
+        return Err(MatMulError::IncompatibleMatrices(lhs.nrows(), lhs.ncols(), rhs.nrows(), rhs.ncols()));
+    }
+    let mut prod = AlignedGF2Mat::zero(lhs.nrows(), rhs.ncols());
+    addmul_checked(&mut prod, lhs, rhs)?;
+    Ok(prod)
 }
 
+/// Safe version of [`addmul_par`], computes `tgt += lhs * rhs` on up to
+/// `threads` threads after validating the dimensions of all three matrices.
+pub fn addmul_par_checked<const ALIGN: usize, T, S1, S2>(tgt: &mut T, lhs: &S1, rhs: &S2, threads: usize) -> Result<(), MatMulError>
+where T: GF2MatLikeMut<ALIGN>,
+    S1: GF2MatLike<ALIGN>,
+    S2: GF2MatLike<ALIGN> {
+    check_addmul_dims(tgt, lhs, rhs)?;
+    if lhs.nrows() == 0 || rhs.nrows() == 0 || rhs.ncols() == 0 {
+        return Ok(());
+    }
+    unsafe { addmul_par(tgt, lhs, rhs, threads) };
+    Ok(())
+}
+
+/// Computes `lhs * rhs` into a freshly allocated matrix, on up to `threads`
+/// threads.
+pub fn mul_par<const ALIGN: usize, S1, S2>(lhs: &S1, rhs: &S2, threads: usize) -> Result<AlignedGF2Mat<ALIGN>, MatMulError>
+where S1: GF2MatLike<ALIGN>,
+    S2: GF2MatLike<ALIGN> {
+    if lhs.ncols() != rhs.nrows() {
+        return Err(MatMulError::IncompatibleMatrices(lhs.nrows(), lhs.ncols(), rhs.nrows(), rhs.ncols()));
+    }
+    let mut prod = AlignedGF2Mat::zero(lhs.nrows(), rhs.ncols());
+    addmul_par_checked(&mut prod, lhs, rhs, threads)?;
+    Ok(prod)
+}
+
+/// Computes `tgt += lhs * rhs`.
+///
+/// # Safety
+/// `tgt` must have as many rows as `lhs` and the same padded width as `rhs`,
+/// and `lhs` must be at least as wide as `rhs` is tall, with any columns
+/// past `rhs.nrows()` zeroed. None of the dimensions may be zero.
+/// See [`addmul_checked`] for a version which checks this.
 pub unsafe fn addmul<const ALIGN: usize, T, S1, S2>(tgt: &mut T, lhs: &S1, rhs: &S2)
+where T: GF2MatLikeMut<ALIGN>,
+    S1: GF2MatLike<ALIGN>,
+    S2: GF2MatLike<ALIGN> {
+    unsafe { addmul_threads(tgt, lhs, rhs, 1) };
+}
+
+/// Computes `tgt += lhs * rhs`, running the sub-products of the recursive
+/// decompositions on up to `threads` threads. Each thread has its own
+/// temporaries, so memory use grows with the number of threads. With 0 or
+/// 1 threads everything runs on the calling thread, as in [`addmul`].
+///
+/// # Safety
+/// Same as [`addmul`], see [`addmul_par_checked`] for a version which
+/// checks the dimensions.
+pub unsafe fn addmul_par<const ALIGN: usize, T, S1, S2>(tgt: &mut T, lhs: &S1, rhs: &S2, threads: usize)
+where T: GF2MatLikeMut<ALIGN>,
+    S1: GF2MatLike<ALIGN>,
+    S2: GF2MatLike<ALIGN> {
+    unsafe { addmul_threads(tgt, lhs, rhs, threads) };
+}
+
+unsafe fn addmul_threads<const ALIGN: usize, T, S1, S2>(tgt: &mut T, lhs: &S1, rhs: &S2, threads: usize)
+where T: GF2MatLikeMut<ALIGN>,
+    S1: GF2MatLike<ALIGN>,
+    S2: GF2MatLike<ALIGN> {
+    unsafe { addmul_with_profile(tgt, lhs, rhs, threads, &TuningProfile::current()) };
+}
+
+/// Same as [`addmul_par`], tuned by `profile` rather than the current one
+pub(crate) unsafe fn addmul_with_profile<const ALIGN: usize, T, S1, S2>(tgt: &mut T, lhs: &S1, rhs: &S2, threads: usize, profile: &TuningProfile)
 where T: GF2MatLikeMut<ALIGN>,
     S1: GF2MatLike<ALIGN>,
     S2: GF2MatLike<ALIGN> {
@@ -42,41 +206,51 @@ where T: GF2MatLikeMut<ALIGN>,
     debug_assert!(lhs.max_ncols() >= rhs.nrows());
 
     let (m, k, n) = (lhs.nrows(), rhs.nrows(), rhs.max_ncols());
-    let min_dim = m.min(k).min(n);
-    if min_dim < STRASSEN_CUTOFF {
-        unsafe { addmul_m4rm(tgt, lhs, rhs) };
+    if m.min(k).min(n) < profile.strassen_cutoff {
+        unsafe { addmul_m4rm_par(tgt, lhs, rhs, threads) };
         return;
     }
-
-    let mut algos = Vec::<AddMulAlgo>::with_capacity(8);
-    let mut exp = 1;
-    let mut recurse_sz = 2 * WINDOW_ALIGN * U8SZ;
-    {
-        let mut res_block_sz = min_dim/2;
-        while res_block_sz > STRASSEN_CUTOFF {
-            recurse_sz *= 2;
-            res_block_sz /= 2;
-            exp += 1;
-        }
+    // windows can't be aligned to more than the matrices they are cut from
+    match profile.window_align.min(ALIGN) {
+        64 => unsafe { addmul_aligned::<ALIGN, 64, _, _, _>(tgt, lhs, rhs, threads, profile) },
+        32 => unsafe { addmul_aligned::<ALIGN, 32, _, _, _>(tgt, lhs, rhs, threads, profile) },
+        _ => unsafe { addmul_aligned::<ALIGN, 16, _, _, _>(tgt, lhs, rhs, threads, profile) },
     }
-    // TODO: finding optimal decompositions for non-powers of 2
-    // specifically 2, 3, 4, 5
-    // TODO: pad instead of peel sometimes
-    let decomp4x4_times = exp/2;
-    let decomp2x2_times = exp - 2*decomp4x4_times;
-    for _ in 0..decomp4x4_times {
-        algos.push(AddMulAlgo::Decomp4x4);
+}
+
+unsafe fn addmul_aligned<const ALIGN: usize, const WINDOW_ALIGN: usize, T, S1, S2>(tgt: &mut T, lhs: &S1, rhs: &S2, threads: usize, profile: &TuningProfile)
+where T: GF2MatLikeMut<ALIGN>,
+    S1: GF2MatLike<ALIGN>,
+    S2: GF2MatLike<ALIGN> {
+    let (m, k, n) = (lhs.nrows(), rhs.nrows(), rhs.max_ncols());
+    let plan = plan_addmul(m, k, n, WINDOW_ALIGN, profile);
+    if plan.algos.len() == 1 {
+        unsafe { addmul_m4rm_par(tgt, lhs, rhs, threads) };
+        return;
     }
-    for _ in 0..decomp2x2_times {
-        algos.push(AddMulAlgo::Decomp2x2);
+    unsafe { addmul_planned::<ALIGN, WINDOW_ALIGN, _, _, _>(tgt, lhs, rhs, &plan, threads) };
+}
+
+/// Computes `tgt += lhs * rhs` by recursing as `plan` says, either padding
+/// the operands or peeling off the strips left over.
+pub(crate) unsafe fn addmul_planned<const ALIGN: usize, const WINDOW_ALIGN: usize, T, S1, S2>(tgt: &mut T, lhs: &S1, rhs: &S2, plan: &Plan, threads: usize)
+where T: GF2MatLikeMut<ALIGN>,
+    S1: GF2MatLike<ALIGN>,
+    S2: GF2MatLike<ALIGN> {
+    // every level has to divide its blocks into whole window chunks
+    let blocks: usize = plan.algos.iter().map(|algo| algo.dim()).product();
+    assert!(plan.recurse_sz.is_multiple_of(blocks*WINDOW_ALIGN*U8SZ));
+    if plan.pad {
+        unsafe { addmul_padded::<ALIGN, WINDOW_ALIGN, _, _, _>(tgt, lhs, rhs, plan, threads) };
+        return;
     }
-    algos.push(AddMulAlgo::M4RM);
-    
+    let (m, k, n) = (lhs.nrows(), rhs.nrows(), rhs.max_ncols());
+    let recurse_sz = plan.recurse_sz;
+
     let mm = m - (m % recurse_sz);
     let kk = k - (k % recurse_sz);
     let nn = n - (n % recurse_sz);
 
-    assert!([mm, kk, nn].iter().all(|x| x % (1<<exp)*WINDOW_ALIGN*U8SZ == 0));
     let chunk_k = kk / (U8SZ*WINDOW_ALIGN);
     let chunk_n = nn / (U8SZ*WINDOW_ALIGN);
 
@@ -88,7 +262,7 @@ where T: GF2MatLikeMut<ALIGN>,
         let lhs_recurse = unsafe { lhs.get_window_unchecked::<WINDOW_ALIGN>(0, 0, mm, chunk_k) };
         let rhs_recurse = unsafe { rhs.get_window_unchecked::<WINDOW_ALIGN>(0, 0, kk, chunk_n) };
 
-        unsafe { addmul_recurse(&mut tgt_recurse, &lhs_recurse, &rhs_recurse, &algos) };
+        unsafe { addmul_recurse_par(&mut tgt_recurse, &lhs_recurse, &rhs_recurse, &plan.algos, threads) };
     }
 
     let a_nwinchunk_cols = lhs.nchunk_cols()*ALIGN/WINDOW_ALIGN;
@@ -97,24 +271,73 @@ where T: GF2MatLikeMut<ALIGN>,
     if n_rem > 0 {
         let b_last_col = unsafe { rhs.get_window_unchecked::<WINDOW_ALIGN>(0, chunk_n, k, b_nwinchunk_cols - chunk_n) };
         let mut c_last_col = unsafe { tgt.get_window_mut_unchecked::<WINDOW_ALIGN>(0, chunk_n, m, b_nwinchunk_cols - chunk_n) };
-        unsafe { addmul_m4rm(&mut c_last_col, &lhs_window, &b_last_col) };
+        unsafe { addmul_m4rm_par(&mut c_last_col, &lhs_window, &b_last_col, threads) };
     }
 
     if m_rem > 0 {
         let a_last_row = unsafe { lhs.get_window_unchecked::<WINDOW_ALIGN>(mm, 0, m_rem, a_nwinchunk_cols) };
         let b_first_col = unsafe { rhs.get_window_unchecked::<WINDOW_ALIGN>(0, 0, k, chunk_n) };
         let mut c_last_row = unsafe { tgt.get_window_mut_unchecked::<WINDOW_ALIGN>(mm, 0, m_rem, chunk_n) };
-        unsafe { addmul_m4rm(&mut c_last_row, &a_last_row, &b_first_col) };
+        unsafe { addmul_m4rm_par(&mut c_last_row, &a_last_row, &b_first_col, threads) };
     }
 
     if k_rem > 0 {
         let a_last_col = unsafe { lhs.get_window_unchecked::<WINDOW_ALIGN>(0, chunk_k, mm, a_nwinchunk_cols - chunk_k) };
         let b_last_row = unsafe { rhs.get_window_unchecked::<WINDOW_ALIGN>(kk, 0, k_rem, chunk_n) };
         let mut c_bulk = unsafe { tgt.get_window_mut_unchecked::<WINDOW_ALIGN>(0, 0, mm, chunk_n) };
-        unsafe { addmul_m4rm(&mut c_bulk, &a_last_col, &b_last_row) };
+        unsafe { addmul_m4rm_par(&mut c_bulk, &a_last_col, &b_last_row, threads) };
     }
 }
 
+/// Runs `plan` on copies of the operands padded with zeros to a multiple
+/// of its `recurse_sz`. Operands which are already a multiple are used in
+/// place, and the product only goes through a padded copy if `tgt` isn't.
+unsafe fn addmul_padded<const ALIGN: usize, const WINDOW_ALIGN: usize, T, S1, S2>(tgt: &mut T, lhs: &S1, rhs: &S2, plan: &Plan, threads: usize)
+where T: GF2MatLikeMut<ALIGN>,
+    S1: GF2MatLike<ALIGN>,
+    S2: GF2MatLike<ALIGN> {
+    let chunk_bits = U8SZ * WINDOW_ALIGN;
+    let (m, k, n) = (lhs.nrows(), rhs.nrows(), rhs.max_ncols());
+    let round_up = |x: usize| x.next_multiple_of(plan.recurse_sz);
+    let (pm, pk, pn) = (round_up(m), round_up(k), round_up(n));
+
+    let lhs_pad = (pm != m || pk != k).then(|| padded_copy::<ALIGN, WINDOW_ALIGN, _>(lhs, pm, pk));
+    let rhs_pad = (pk != k || pn != n).then(|| padded_copy::<ALIGN, WINDOW_ALIGN, _>(rhs, pk, pn));
+    let lhs_window = match &lhs_pad {
+        Some(pad) => unsafe { pad.get_window_unchecked::<WINDOW_ALIGN>(0, 0, pm, pk / chunk_bits) },
+        None => unsafe { lhs.get_window_unchecked::<WINDOW_ALIGN>(0, 0, m, k / chunk_bits) },
+    };
+    let rhs_window = match &rhs_pad {
+        Some(pad) => unsafe { pad.get_window_unchecked::<WINDOW_ALIGN>(0, 0, pk, pn / chunk_bits) },
+        None => unsafe { rhs.get_window_unchecked::<WINDOW_ALIGN>(0, 0, k, n / chunk_bits) },
+    };
+
+    let mut tgt_window = unsafe { tgt.get_window_mut_unchecked::<WINDOW_ALIGN>(0, 0, m, n / chunk_bits) };
+    if pm == m && pn == n {
+        unsafe { addmul_recurse_par(&mut tgt_window, &lhs_window, &rhs_window, &plan.algos, threads) };
+    } else {
+        let mut prod = AlignedGF2Mat::<WINDOW_ALIGN>::zero(pm, pn);
+        unsafe { addmul_recurse_par(&mut prod, &lhs_window, &rhs_window, &plan.algos, threads) };
+        unsafe { tgt_window.add_unchecked(&prod.get_window_unchecked::<WINDOW_ALIGN>(0, 0, m, n / chunk_bits)) };
+    }
+}
+
+/// A copy of `src` with zero rows and columns added up to `nrows` by
+/// `ncols`. Columns of `src` past `ncols` are dropped, they must be zero.
+fn padded_copy<const ALIGN: usize, const WINDOW_ALIGN: usize, S>(src: &S, nrows: usize, ncols: usize) -> AlignedGF2Mat<WINDOW_ALIGN>
+where S: GF2MatLike<ALIGN> {
+    let mut pad = AlignedGF2Mat::<WINDOW_ALIGN>::zero(nrows, ncols);
+    let nbytes = src.nbyte_cols().min(pad.nbyte_cols());
+    for i in 0..src.nrows() {
+        let src_row = unsafe { src.row_slice_unchecked(i) };
+        let pad_row = unsafe { pad.row_slice_mut_unchecked(i) };
+        pad_row[..nbytes].copy_from_slice(&src_row[..nbytes]);
+    }
+    pad
+}
+
+/// Computes `tgt += lhs * rhs` recursing through `algos`, the blocks being
+/// cut on `ALIGN` boundaries
 pub unsafe fn addmul_recurse<const ALIGN: usize, T, S1, S2>(tgt: &mut T, lhs: &S1, rhs: &S2, algos: &[AddMulAlgo])
 where T: GF2MatLikeMut<ALIGN>,
     S1: GF2MatLike<ALIGN>,
@@ -130,20 +353,61 @@ where T: GF2MatLikeMut<ALIGN>,
             unsafe { addmul_m4rm(tgt, lhs, rhs) }
         },
         AddMulAlgo::Decomp2x2 => {
-            unsafe { addmul_decomp2x2::<ALIGN, WINDOW_ALIGN, _, _, _>(tgt, lhs, rhs, algos) };
+            unsafe { addmul_decomp2x2::<ALIGN, ALIGN, _, _, _>(tgt, lhs, rhs, algos) };
+        },
+        #[cfg(feature = "decomp3x3")]
+        AddMulAlgo::Decomp3x3 => {
+            unsafe { addmul_decomp3x3::<ALIGN, ALIGN, _, _, _>(tgt, lhs, rhs, algos) };
+        },
+        AddMulAlgo::Decomp4x4 => {
+            unsafe { addmul_decomp4x4::<ALIGN, ALIGN, _, _, _>(tgt, lhs, rhs, algos) }; 
+        },
+        #[cfg(feature = "decomp5x5")]
+        AddMulAlgo::Decomp5x5 => {
+            unsafe { addmul_decomp5x5::<ALIGN, ALIGN, _, _, _>(tgt, lhs, rhs, algos) };
+        },
+        // these increase compilation time a lot, so they are behind features
+        #[allow(unreachable_patterns)]
+        AddMulAlgo::Decomp3x3 | AddMulAlgo::Decomp5x5 => {
+            panic!("{algo:?} needs the feature of the same name");
+        }
+    }
+}
+
+/// Same as [`addmul_recurse`], with the sub-products of the decompositions
+/// running on up to `threads` threads
+pub(crate) unsafe fn addmul_recurse_par<const ALIGN: usize, T, S1, S2>(tgt: &mut T, lhs: &S1, rhs: &S2, algos: &[AddMulAlgo], threads: usize)
+where T: GF2MatLikeMut<ALIGN>,
+    S1: GF2MatLike<ALIGN>,
+    S2: GF2MatLike<ALIGN> {
+
+    if threads <= 1 {
+        unsafe { addmul_recurse(tgt, lhs, rhs, algos) };
+        return;
+    }
+    let (algo, rest) = algos.split_first().expect("No algo to recurse into");
+
+    match algo {
+        AddMulAlgo::M4RM => {
+            unsafe { addmul_m4rm_par(tgt, lhs, rhs, threads) }
+        },
+        AddMulAlgo::Decomp2x2 => {
+            unsafe { addmul_decomp2x2_par::<ALIGN, ALIGN, _, _, _>(tgt, lhs, rhs, rest, threads) };
         },
+        #[cfg(feature = "decomp3x3")]
         AddMulAlgo::Decomp3x3 => {
-            // not used atm, and increases compilation time
-            //unsafe { addmul_decomp3x3::<ALIGN, WINDOW_ALIGN, _, _, _>(tgt, lhs, rhs, algos) };
-            todo!();
+            unsafe { addmul_decomp3x3_par::<ALIGN, ALIGN, _, _, _>(tgt, lhs, rhs, rest, threads) };
         },
         AddMulAlgo::Decomp4x4 => {
-            unsafe { addmul_decomp4x4::<ALIGN, WINDOW_ALIGN, _, _, _>(tgt, lhs, rhs, algos) }; 
+            unsafe { addmul_decomp4x4_par::<ALIGN, ALIGN, _, _, _>(tgt, lhs, rhs, rest, threads) };
         },
+        #[cfg(feature = "decomp5x5")]
         AddMulAlgo::Decomp5x5 => {
-            // not used atm, and increases compilation time
-            //unsafe { addmul_decomp5x5::<ALIGN, WINDOW_ALIGN, _, _, _>(tgt, lhs, rhs, algos) };
-            todo!();
+            unsafe { addmul_decomp5x5_par::<ALIGN, ALIGN, _, _, _>(tgt, lhs, rhs, rest, threads) };
+        },
+        #[allow(unreachable_patterns)]
+        AddMulAlgo::Decomp3x3 | AddMulAlgo::Decomp5x5 => {
+            panic!("{algo:?} needs the feature of the same name");
         }
     }
-}
\ No newline at end of file
+}
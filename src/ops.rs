@@ -0,0 +1,164 @@
+/*!
+Operator overloads for owned matrices and windows.
+
+All binary operators take their operands by reference and allocate a
+fresh `AlignedGF2Mat` for the result, while the assigning versions work
+in place. The dimensions are always checked, a mismatch panics with the
+corresponding `MatAddError` or `MatMulError` rather than reaching the
+unchecked kernels.
+*/
+
+use std::ops::{
+    Add, AddAssign, BitAnd, BitAndAssign, BitOr, BitOrAssign, Mul, MulAssign, Not,
+};
+
+use crate::gf2mat::*;
+use crate::mul::mul;
+use crate::view::{GF2MatView, GF2MatViewMut};
+
+/// Applies `f` to every pair of rows of `dst` and `src`, after checking
+/// that the two matrices have the same dimensions.
+fn zip_rows_checked<const ALIGN: usize, T, S>(
+    dst: &mut T,
+    src: &S,
+    f: unsafe fn(&mut [u8], &[u8]),
+) -> Result<(), MatAddError>
+where
+    T: GF2MatLikeMut<ALIGN>,
+    S: GF2MatLike<ALIGN>,
+{
+    if dst.nrows() != src.nrows()
+        || dst.ncols() != src.ncols()
+        || dst.nchunk_cols() != src.nchunk_cols()
+    {
+        return Err(MatAddError::IncompatibleMatrices(
+            dst.nrows(),
+            dst.ncols(),
+            src.nrows(),
+            src.ncols(),
+        ));
+    }
+    if dst.nchunk_cols() == 0 {
+        return Ok(());
+    }
+    for i in 0..dst.nrows() {
+        unsafe { f(dst.row_slice_mut_unchecked(i), src.row_slice_unchecked(i)) };
+    }
+    Ok(())
+}
+
+fn not_in_place<const ALIGN: usize>(mat: &mut AlignedGF2Mat<ALIGN>) {
+    for i in 0..mat.nrows() {
+        unsafe { mat.row_slice_mut_unchecked(i) }
+            .iter_mut()
+            .for_each(|x| *x = !*x);
+    }
+    mat.clear_padding();
+}
+
+macro_rules! impl_binops {
+    ($($lhs:ty),*) => {$(
+        impl<const ALIGN: usize, S: GF2MatLike<ALIGN>> Add<&S> for &$lhs {
+            type Output = AlignedGF2Mat<ALIGN>;
+
+            fn add(self, rhs: &S) -> AlignedGF2Mat<ALIGN> {
+                let mut res = self.to_mat();
+                res += rhs;
+                res
+            }
+        }
+
+        impl<const ALIGN: usize, S: GF2MatLike<ALIGN>> BitAnd<&S> for &$lhs {
+            type Output = AlignedGF2Mat<ALIGN>;
+
+            fn bitand(self, rhs: &S) -> AlignedGF2Mat<ALIGN> {
+                let mut res = self.to_mat();
+                res &= rhs;
+                res
+            }
+        }
+
+        impl<const ALIGN: usize, S: GF2MatLike<ALIGN>> BitOr<&S> for &$lhs {
+            type Output = AlignedGF2Mat<ALIGN>;
+
+            fn bitor(self, rhs: &S) -> AlignedGF2Mat<ALIGN> {
+                let mut res = self.to_mat();
+                res |= rhs;
+                res
+            }
+        }
+
+        impl<const ALIGN: usize, S: GF2MatLike<ALIGN>> Mul<&S> for &$lhs {
+            type Output = AlignedGF2Mat<ALIGN>;
+
+            fn mul(self, rhs: &S) -> AlignedGF2Mat<ALIGN> {
+                mul(self, rhs).unwrap_or_else(|e| panic!("{e}"))
+            }
+        }
+
+        impl<const ALIGN: usize> Not for &$lhs {
+            type Output = AlignedGF2Mat<ALIGN>;
+
+            fn not(self) -> AlignedGF2Mat<ALIGN> {
+                let mut res = self.to_mat();
+                not_in_place(&mut res);
+                res
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_assign_ops {
+    ($($lhs:ty),*) => {$(
+        impl<const ALIGN: usize, S: GF2MatLike<ALIGN>> AddAssign<&S> for $lhs {
+            fn add_assign(&mut self, rhs: &S) {
+                self.add_checked(rhs).unwrap_or_else(|e| panic!("{e}"));
+            }
+        }
+
+        impl<const ALIGN: usize, S: GF2MatLike<ALIGN>> BitAndAssign<&S> for $lhs {
+            fn bitand_assign(&mut self, rhs: &S) {
+                zip_rows_checked(self, rhs, and_aligned_slices::<ALIGN>)
+                    .unwrap_or_else(|e| panic!("{e}"));
+            }
+        }
+
+        impl<const ALIGN: usize, S: GF2MatLike<ALIGN>> BitOrAssign<&S> for $lhs {
+            fn bitor_assign(&mut self, rhs: &S) {
+                zip_rows_checked(self, rhs, or_aligned_slices::<ALIGN>)
+                    .unwrap_or_else(|e| panic!("{e}"));
+            }
+        }
+
+        impl<const ALIGN: usize, S: GF2MatLike<ALIGN>> MulAssign<&S> for $lhs {
+            fn mul_assign(&mut self, rhs: &S) {
+                let prod = &*self * rhs;
+                if prod.ncols() != self.ncols() {
+                    panic!(
+                        "{}",
+                        MatMulError::DimMismatch(prod.nrows(), prod.ncols(), self.nrows(), self.ncols())
+                    );
+                }
+                copy_cols(self, &prod);
+            }
+        }
+    )*};
+}
+
+impl_binops!(
+    AlignedGF2Mat<ALIGN>,
+    GF2MatWindow<'_, ALIGN>,
+    GF2MatWindowMut<'_, ALIGN>,
+    GF2MatView<'_, ALIGN>,
+    GF2MatViewMut<'_, ALIGN>
+);
+impl_assign_ops!(AlignedGF2Mat<ALIGN>, GF2MatWindowMut<'_, ALIGN>, GF2MatViewMut<'_, ALIGN>);
+
+impl<const ALIGN: usize> Not for AlignedGF2Mat<ALIGN> {
+    type Output = AlignedGF2Mat<ALIGN>;
+
+    fn not(mut self) -> AlignedGF2Mat<ALIGN> {
+        not_in_place(&mut self);
+        self
+    }
+}
@@ -0,0 +1,157 @@
+/*!
+Running the sub-products of the recursive decompositions on several threads.
+
+The decompositions in `decomp*x*.rs` are straight-line code which sums blocks
+of `A` and `B` into shared temporaries, multiplies them and adds the result
+to blocks of `C`. To run the products independently, the same code is run
+once on stand-ins for the blocks which only track which blocks or products
+each of them sums. This gives the scheme as data: for every product the
+blocks of `A` and `B` it sums and the blocks of `C` it goes into.
+
+Workers then take products off a shared counter, each with temporaries of
+its own, and add their results to `C` under a lock per block. Products
+whose operand is a single block use it in place rather than copying it.
+*/
+
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::gf2mat::*;
+use crate::mul::{addmul_recurse_par, AddMulAlgo};
+
+/// Stands in for a block of a decomposition, or one of its temporaries,
+/// while recording the scheme. Holds the blocks or products it is the sum of.
+#[derive(Clone, Default)]
+pub(crate) struct Sym {
+    terms: Vec<usize>,
+}
+
+impl Sym {
+    /// The blocks of a `DIM` by `DIM` division, numbered row by row
+    pub fn division<const DIM: usize>() -> [[Sym; DIM]; DIM] {
+        std::array::from_fn(|i| std::array::from_fn(|j| Sym { terms: vec![i * DIM + j] }))
+    }
+
+    fn toggle(&mut self, term: usize) {
+        match self.terms.iter().position(|&t| t == term) {
+            Some(pos) => {
+                self.terms.swap_remove(pos);
+            }
+            None => self.terms.push(term),
+        }
+    }
+
+    pub unsafe fn add_unchecked(&mut self, rhs: &Sym) {
+        rhs.terms.iter().for_each(|&t| self.toggle(t));
+    }
+
+    pub unsafe fn set_to_sum_unchecked(&mut self, lhs: &Sym, rhs: &Sym) {
+        self.clear();
+        unsafe {
+            self.add_unchecked(lhs);
+            self.add_unchecked(rhs);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.terms.clear();
+    }
+}
+
+/// The products seen while recording, as the blocks of `A` and `B` they sum
+#[derive(Default)]
+pub(crate) struct Recorder {
+    products: RefCell<Vec<(Vec<usize>, Vec<usize>)>>,
+}
+
+/// Stands in for `addmul_recurse` while recording
+pub(crate) unsafe fn record_addmul(tgt: &mut Sym, lhs: &Sym, rhs: &Sym, recorder: &&Recorder) {
+    let mut products = recorder.products.borrow_mut();
+    tgt.toggle(products.len());
+    products.push((lhs.terms.clone(), rhs.terms.clone()));
+}
+
+struct Product {
+    a: Vec<usize>,
+    b: Vec<usize>,
+    c: Vec<usize>,
+}
+
+/// A decomposition as data, the blocks numbered row by row
+pub(crate) struct Scheme {
+    products: Vec<Product>,
+}
+
+impl Scheme {
+    pub fn from_recording<const DIM: usize>(recorder: Recorder, c_blks: &[[Sym; DIM]; DIM]) -> Self {
+        let mut products: Vec<Product> = recorder
+            .products
+            .into_inner()
+            .into_iter()
+            .map(|(a, b)| Product { a, b, c: Vec::new() })
+            .collect();
+        for (c, blk) in c_blks.as_flattened().iter().enumerate() {
+            for &p in &blk.terms {
+                products[p].c.push(c);
+            }
+        }
+        Self { products }
+    }
+}
+
+/// The sum of some blocks, which is the block itself for a single one and
+/// otherwise put together in `tmp`
+fn sum_blocks<'a, const WINDOW_ALIGN: usize>(
+    tmp: &'a mut AlignedGF2Mat<WINDOW_ALIGN>,
+    blks: &[GF2MatWindow<'a, WINDOW_ALIGN>],
+    terms: &[usize],
+) -> GF2MatWindow<'a, WINDOW_ALIGN> {
+    if let [t] = terms {
+        return blks[*t];
+    }
+    tmp.clear();
+    for &t in terms {
+        unsafe { tmp.add_unchecked(&blks[t]) };
+    }
+    unsafe { tmp.get_window_unchecked(0, 0, tmp.nrows(), tmp.nchunk_cols()) }
+}
+
+/// Runs `scheme` on up to `threads` threads, the blocks being `mm` by `kk`
+/// for `A`, `kk` by `nn` for `B` and `mm` by `nn` for `C`. With more threads
+/// than products the leftover ones go to the products' own recursion.
+pub(crate) unsafe fn run_scheme<const WINDOW_ALIGN: usize>(
+    scheme: &Scheme,
+    a_blks: &[GF2MatWindow<'_, WINDOW_ALIGN>],
+    b_blks: &[GF2MatWindow<'_, WINDOW_ALIGN>],
+    c_blks: Vec<GF2MatWindowMut<'_, WINDOW_ALIGN>>,
+    (mm, kk, nn): (usize, usize, usize),
+    algos: &[AddMulAlgo],
+    threads: usize,
+) {
+    let workers = threads.min(scheme.products.len());
+    let sub_threads = threads / workers;
+    let c_blks: Vec<Mutex<_>> = c_blks.into_iter().map(Mutex::new).collect();
+    let next = AtomicUsize::new(0);
+    let work = || {
+        let mut tmp_mk = AlignedGF2Mat::<WINDOW_ALIGN>::zero(mm, kk);
+        let mut tmp_kn = AlignedGF2Mat::<WINDOW_ALIGN>::zero(kk, nn);
+        let mut tmp_mn = AlignedGF2Mat::<WINDOW_ALIGN>::zero(mm, nn);
+        while let Some(p) = scheme.products.get(next.fetch_add(1, Ordering::Relaxed)) {
+            let lhs = sum_blocks(&mut tmp_mk, a_blks, &p.a);
+            let rhs = sum_blocks(&mut tmp_kn, b_blks, &p.b);
+            tmp_mn.clear();
+            unsafe { addmul_recurse_par(&mut tmp_mn, &lhs, &rhs, algos, sub_threads) };
+            for &c in &p.c {
+                let mut blk = c_blks[c].lock().unwrap();
+                unsafe { blk.add_unchecked(&tmp_mn) };
+            }
+        }
+    };
+    std::thread::scope(|s| {
+        for _ in 1..workers {
+            s.spawn(work);
+        }
+        work();
+    });
+}
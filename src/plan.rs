@@ -0,0 +1,156 @@
+/*!
+Choosing how `addmul` recurses for a product of a given shape.
+
+Every sequence of decompositions which follows the order of the profile's
+`decomp_order`, and brings the smallest dimension down to at most its
+`strassen_cutoff`, is a candidate. A sequence whose block sizes multiply to
+`d` needs every recursed dimension to be a multiple of `d` window chunks,
+and what is left over is either peeled off as strips done by M4RM, or the
+operands are copied into zero-padded matrices which are a multiple all the
+way.
+
+Each candidate is priced in estimated nanoseconds. M4RM is charged per word
+it adds from a table, more so for small blocks where the tables are built
+for few rows, and for wide ones whose tables don't fit in cache. The passes
+the decompositions make over blocks and the copies for padding are charged
+per word, which covers allocating and zeroing the temporaries too. The
+constants are fitted to timings from one machine, so what they get right is
+which plan is cheaper rather than by how much.
+*/
+
+use crate::mul::AddMulAlgo;
+use crate::tune::TuningProfile;
+
+const U8SZ: usize = u8::BITS as usize;
+const WORD_BITS: f64 = u64::BITS as f64;
+
+/// Nanoseconds per word M4RM adds from a table
+const M4RM_NS: f64 = 0.08;
+/// The smaller of the height and width at which M4RM is twice as slow per
+/// word as for large blocks
+const M4RM_SMALL: f64 = 1024.0;
+/// The table size at which M4RM is twice as slow per word
+const M4RM_TBL_BYTES: f64 = (64 << 10) as f64;
+/// Nanoseconds per word of a pass over a block
+const PASS_NS: f64 = 3.0;
+
+/// How `addmul` computes a product too large for M4RM alone
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Plan {
+    /// The decompositions to recurse through, ending in M4RM
+    pub algos: Vec<AddMulAlgo>,
+    /// Every recursed dimension is a multiple of this
+    pub recurse_sz: usize,
+    /// Whether the dimensions are padded up to a multiple of `recurse_sz`,
+    /// rather than the leftover strips being peeled off
+    pub pad: bool,
+}
+
+/// The cheapest plan for an `m` by `k` times `k` by `n` product, cut into
+/// windows aligned to `window_align` bytes
+pub(crate) fn plan_addmul(m: usize, k: usize, n: usize, window_align: usize, profile: &TuningProfile) -> Plan {
+    // the rows of a window chunk, the granularity of every recursed dimension
+    let chunk_bits = window_align * U8SZ;
+    let min_dim = m.min(k).min(n);
+    let mut best: Option<(f64, Plan)> = None;
+    let mut consider = |levels: &[AddMulAlgo]| {
+        let blocks: usize = levels.iter().map(|algo| algo.dim()).product();
+        let recurse_sz = blocks * chunk_bits;
+        if min_dim / blocks > profile.strassen_cutoff {
+            return;
+        }
+        let mut algos = levels.to_vec();
+        algos.push(AddMulAlgo::M4RM);
+        for pad in [false, true] {
+            let cost = if pad { pad_cost(&algos, recurse_sz, m, k, n) } else { peel_cost(&algos, recurse_sz, m, k, n) };
+            if best.as_ref().is_none_or(|(best_cost, _)| cost < *best_cost) {
+                best = Some((cost, Plan { algos: algos.clone(), recurse_sz, pad }));
+            }
+        }
+    };
+    for_each_sequence(&mut Vec::new(), &profile.decomp_order, min_dim / chunk_bits, &mut consider);
+    // an order too short to get under the cutoff leaves only M4RM
+    best.map_or_else(|| Plan { algos: vec![AddMulAlgo::M4RM], recurse_sz: chunk_bits, pad: false }, |(_, plan)| plan)
+}
+
+/// Calls `f` with every sequence of decompositions from `order`, each used
+/// no earlier in the sequence than those before it in `order`, whose block
+/// sizes multiply to at most `max_blocks`, extending `prefix`
+fn for_each_sequence(prefix: &mut Vec<AddMulAlgo>, order: &[AddMulAlgo], max_blocks: usize, f: &mut impl FnMut(&[AddMulAlgo])) {
+    f(prefix);
+    for (i, &algo) in order.iter().enumerate() {
+        if algo.dim() <= max_blocks {
+            prefix.push(algo);
+            for_each_sequence(prefix, &order[i..], max_blocks / algo.dim(), f);
+            prefix.pop();
+        }
+    }
+}
+
+/// Peeling recurses on the largest multiples of `recurse_sz` and does the
+/// strips around them with M4RM
+fn peel_cost(algos: &[AddMulAlgo], recurse_sz: usize, m: usize, k: usize, n: usize) -> f64 {
+    let (mm, kk, nn) = (m - m % recurse_sz, k - k % recurse_sz, n - n % recurse_sz);
+    if mm == 0 || kk == 0 || nn == 0 {
+        return f64::INFINITY;
+    }
+    recurse_cost(algos, mm, kk, nn)
+        + m4rm_cost(m, k, n - nn)
+        + m4rm_cost(m - mm, k, nn)
+        + m4rm_cost(mm, k - kk, nn)
+}
+
+/// Padding recurses on the dimensions rounded up to multiples of
+/// `recurse_sz`, after copying the operands which need it. A padded
+/// product is zeroed first and added to the target at the end.
+fn pad_cost(algos: &[AddMulAlgo], recurse_sz: usize, m: usize, k: usize, n: usize) -> f64 {
+    let round_up = |x: usize| x.next_multiple_of(recurse_sz);
+    let (pm, pk, pn) = (round_up(m), round_up(k), round_up(n));
+    if (pm, pk, pn) == (m, k, n) {
+        return f64::INFINITY;
+    }
+    let mut cost = recurse_cost(algos, pm, pk, pn);
+    if pm != m || pk != k {
+        cost += add_cost(pm, pk);
+    }
+    if pk != k || pn != n {
+        cost += add_cost(pk, pn);
+    }
+    if pm != m || pn != n {
+        cost += 2.0 * add_cost(pm, pn);
+    }
+    cost
+}
+
+/// Recursing through `algos` on dimensions which divide evenly
+fn recurse_cost(algos: &[AddMulAlgo], m: usize, k: usize, n: usize) -> f64 {
+    let (algo, rest) = algos.split_first().expect("No algo to recurse into");
+    if rest.is_empty() {
+        return m4rm_cost(m, k, n);
+    }
+    let d = algo.dim();
+    let (mm, kk, nn) = (m / d, k / d, n / d);
+    let (mk_passes, kn_passes, mn_passes) = algo.block_passes();
+    mk_passes as f64 * add_cost(mm, kk)
+        + kn_passes as f64 * add_cost(kk, nn)
+        + mn_passes as f64 * add_cost(mm, nn)
+        + algo.nproducts() as f64 * recurse_cost(rest, mm, kk, nn)
+}
+
+/// A pass over an `nrows` by `ncols` block, as when adding to it
+fn add_cost(nrows: usize, ncols: usize) -> f64 {
+    nrows as f64 * ncols as f64 / WORD_BITS * PASS_NS
+}
+
+/// M4RM builds a table for every 8 rows of the right-hand side, and adds a
+/// row of it to every row of the target
+fn m4rm_cost(m: usize, k: usize, n: usize) -> f64 {
+    if m == 0 || k == 0 || n == 0 {
+        return 0.0;
+    }
+    let row_words = n as f64 / WORD_BITS;
+    let tbl_bytes = 256.0 * n as f64 / U8SZ as f64;
+    let ntbls = k.div_ceil(8) as f64;
+    let slowdown = (1.0 + M4RM_SMALL / m.min(n) as f64) * (1.0 + tbl_bytes / M4RM_TBL_BYTES);
+    ntbls * (m as f64 + 256.0) * row_words * M4RM_NS * slowdown
+}
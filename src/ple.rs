@@ -0,0 +1,382 @@
+/*!
+PLE and PLUQ decompositions. PLE is `A = P * L * E` with `P` a row
+permutation, `L` unit lower triangular and `E` in row echelon form, computed
+in place like m4ri does.
+
+The rows of `E` end up in the first `rank` rows of the matrix. `L` is stored
+compactly: its column `t` lives in pivot column `q[t]` below row `t`, which
+is free since `E` is zero there. All remaining entries are zero afterwards.
+
+Narrow matrices are handled by a Four-Russians elimination similar to
+`echelonize`. Wider ones are split into a left and a right half of columns,
+the left half is decomposed recursively, the top right block is solved
+against `L` and the Schur complement in the bottom right is updated with
+`addmul` before it is decomposed in turn.
+*/
+
+use crate::echelon::strip_width;
+use crate::gf2mat::*;
+use crate::m4rm::{tabulate_m4rm, GRAY_TBL, MAX_TBL_SZ};
+use crate::mul::{addmul, WINDOW_ALIGN};
+use crate::transpose::transpose_into;
+
+const U8SZ: usize = u8::BITS as usize;
+/// Matrices with at most this many columns are decomposed with M4RI
+const PLE_CUTOFF: usize = 4096;
+/// Compactly stored triangular systems with at most this many rows are
+/// solved row by row. The pivot columns of such an `L` are scattered, so
+/// unlike `trsm.rs` there are no Four-Russians tables for the base case and
+/// handing blocks to `addmul` pays off much earlier than its 1024.
+const COMPACT_TRSM_CUTOFF: usize = 128;
+/// Column swaps go through the transpose once more than one in this many
+/// columns moves
+const COL_SWAP_RATIO: usize = 16;
+
+/// Swaps row `i` with row `swaps[i]` for every `i` in order
+pub(crate) fn apply_swaps<const ALIGN: usize, T>(mat: &mut T, swaps: &[usize])
+where
+    T: GF2MatLikeMut<ALIGN> + ?Sized,
+{
+    for (i, &p) in swaps.iter().enumerate() {
+        unsafe { mat.swap_rows_unchecked(i, p) };
+    }
+}
+
+/// Swaps column `j` with column `swaps[j]` for every `j`, in order or in
+/// reverse order. A few swaps are done bit by bit, many by swapping the rows
+/// of the transpose, which is much cheaper than touching every bit of every
+/// row once most columns move.
+pub(crate) fn apply_col_swaps<const ALIGN: usize, T>(mat: &mut T, swaps: &[usize], reverse: bool)
+where
+    T: GF2MatLikeMut<ALIGN> + ?Sized,
+{
+    let moved = swaps.iter().enumerate().filter(|&(j, &q)| q != j).count();
+    if moved * COL_SWAP_RATIO > mat.ncols() {
+        let mut tr = AlignedGF2Mat::<ALIGN>::zero(mat.ncols(), mat.nrows());
+        transpose_into(&mut tr, mat);
+        let order: Box<dyn Iterator<Item = (usize, &usize)>> = if reverse {
+            Box::new(swaps.iter().enumerate().rev())
+        } else {
+            Box::new(swaps.iter().enumerate())
+        };
+        for (j, &q) in order {
+            unsafe { tr.swap_rows_unchecked(j, q) };
+        }
+        transpose_into(mat, &tr);
+        return;
+    }
+    let swap_bits = |row: &mut [u8], j: usize, q: usize| {
+        if q != j {
+            let (a, b) = (read_bits(row, j, 1), read_bits(row, q, 1));
+            write_bits(row, j, 1, b);
+            write_bits(row, q, 1, a);
+        }
+    };
+    for i in 0..mat.nrows() {
+        let row = unsafe { mat.row_slice_mut_unchecked(i) };
+        if reverse {
+            swaps.iter().enumerate().rev().for_each(|(j, &q)| swap_bits(row, j, q));
+        } else {
+            swaps.iter().enumerate().for_each(|(j, &q)| swap_bits(row, j, q));
+        }
+    }
+}
+
+/// Finds up to `k` pivots in columns `c..c + k` among the rows from `r` on and
+/// moves them to rows `r..`. Each new pivot row is reduced by the earlier
+/// ones, which leaves their coefficients, i.e. its entries of `L`, behind in
+/// their pivot columns. Row `t` of `piv` is set to pivot row `r + t` with
+/// everything up to and including its pivot cleared, that is the part which
+/// is added to rows being reduced. Stops at the first column without a pivot
+/// and returns the number of pivots found.
+unsafe fn ple_strip<const ALIGN: usize, T, P>(
+    mat: &mut T,
+    piv: &mut P,
+    r: usize,
+    c: usize,
+    k: usize,
+    perm: &mut [usize],
+) -> usize
+where
+    T: GF2MatLikeMut<ALIGN> + ?Sized,
+    P: GF2MatLikeMut<ALIGN>,
+{
+    let mut piv_strips = [0u64; MAX_TBL_SZ];
+    for j in 0..k {
+        // bit j of a row's strip once it is reduced by the pivots we have,
+        // worked out on the strip alone so candidates can be left untouched
+        let reduced = |s: u64| {
+            (0..j).fold(s, |s, t| if (s >> t) & 1 == 1 { s ^ piv_strips[t] } else { s })
+        };
+        let Some(p) = (r + j..mat.nrows()).find(|&i| {
+            let s = read_bits(unsafe { mat.row_slice_unchecked(i) }, c, j + 1);
+            (reduced(s) >> j) & 1 == 1
+        }) else {
+            return j;
+        };
+        unsafe { mat.swap_rows_unchecked(p, r + j) };
+        perm[r + j] = p;
+        for t in 0..j {
+            if unsafe { mat.get_unchecked(r + j, c + t) } {
+                unsafe { mat.add_row_from_mat(piv, t, r + j) };
+            }
+        }
+        let dst = unsafe { piv.row_slice_mut_unchecked(j) };
+        dst.copy_from_slice(unsafe { mat.row_slice_unchecked(r + j) });
+        clear_prefix(dst, c + j + 1);
+        piv_strips[j] = read_bits(dst, c, k);
+    }
+    k
+}
+
+/// The base case, PLE by Four-Russians elimination. The table is built from
+/// the pivot rows without their `L` part, so after adding it a row holds its
+/// own coefficients in the strip. Returns the pivot columns, with
+/// `stop_at_gap` only up to the first column without a pivot.
+fn ple_m4ri<const ALIGN: usize, T>(mat: &mut T, perm: &mut [usize], stop_at_gap: bool) -> Vec<usize>
+where
+    T: GF2MatLikeMut<ALIGN> + ?Sized,
+{
+    let (nrows, ncols) = (mat.nrows(), mat.ncols());
+    let mut pivots = Vec::new();
+    if nrows == 0 || ncols == 0 {
+        return pivots;
+    }
+    let chunk_bits = ALIGN * U8SZ;
+    let k = strip_width(nrows);
+    let mut tbl = AlignedGF2Mat::<ALIGN>::zero(1 << k, mat.max_ncols());
+    let mut piv = AlignedGF2Mat::<ALIGN>::zero(k, mat.max_ncols());
+    // strip bits of a row to the table row which clears it
+    let mut lookup = [0usize; 1 << MAX_TBL_SZ];
+
+    let (mut r, mut c) = (0, 0);
+    while r < nrows && c < ncols {
+        let chunk = c / chunk_bits;
+        let nchunks = mat.nchunk_cols() - chunk;
+        let (mut left, mut rest) =
+            unsafe { mat.get_window_mut_unchecked::<ALIGN>(0, 0, nrows, mat.nchunk_cols()) }
+                .split_chunk_cols_mut(chunk);
+        let mut piv_rest = unsafe { piv.get_window_mut_unchecked::<ALIGN>(0, chunk, k, nchunks) };
+        let mut tbl_rest = unsafe { tbl.get_window_mut_unchecked::<ALIGN>(0, chunk, 1 << k, nchunks) };
+        let lc = c - chunk * chunk_bits;
+
+        let kbar = unsafe { ple_strip(&mut rest, &mut piv_rest, r, lc, k.min(ncols - c), perm) };
+        if kbar == 0 {
+            if stop_at_gap {
+                break;
+            }
+            c += 1;
+            continue;
+        }
+        // the swaps have to move the parts of `L` left of the window too
+        if chunk != 0 {
+            for (i, &p) in (r..).zip(&perm[r..r + kbar]) {
+                unsafe { left.swap_rows_unchecked(i, p) };
+            }
+        }
+        pivots.extend(c..c + kbar);
+        unsafe { tabulate_m4rm(&mut tbl_rest, &piv_rest, 0, kbar) };
+        // adding the combination x of pivot rows leaves x in the strip, so a
+        // row with strip s wants the x for which s ^ x is x's own strip
+        for x in 0..1usize << kbar {
+            let tbl_row = GRAY_TBL[x] as usize;
+            let strip = read_bits(unsafe { tbl_rest.row_slice_unchecked(tbl_row) }, lc, kbar);
+            lookup[x ^ strip as usize] = tbl_row;
+        }
+        for i in r + kbar..nrows {
+            let s = read_bits(unsafe { rest.row_slice_unchecked(i) }, lc, kbar);
+            if s != 0 {
+                unsafe { rest.add_row_from_mat(&tbl_rest, lookup[s as usize], i) };
+            }
+        }
+        r += kbar;
+        c += kbar;
+    }
+    pivots
+}
+
+/// `tgt += lhs * S` where row `q[t]` of `S` is row `t` of `rhs` and all
+/// other rows are zero, i.e. multiplies by the columns `q` of `lhs`.
+unsafe fn addmul_scattered<const ALIGN: usize, T, S1, S2>(tgt: &mut T, lhs: &S1, rhs: &S2, q: &[usize])
+where
+    T: GF2MatLikeMut<ALIGN>,
+    S1: GF2MatLike<ALIGN>,
+    S2: GF2MatLike<ALIGN>,
+{
+    if tgt.nrows() == 0 || q.is_empty() {
+        return;
+    }
+    if q.len() == lhs.max_ncols() {
+        // q is increasing, so this means q[t] == t
+        unsafe { addmul(tgt, lhs, rhs) };
+        return;
+    }
+    let mut scattered = AlignedGF2Mat::<ALIGN>::zero(lhs.max_ncols(), rhs.max_ncols());
+    for (t, &row) in q.iter().enumerate() {
+        unsafe { scattered.row_slice_mut_unchecked(row) }
+            .copy_from_slice(unsafe { rhs.row_slice_unchecked(t) });
+    }
+    unsafe { addmul(tgt, lhs, &scattered) };
+}
+
+/// Solves `L * X = B` in place of `B`, for `L` stored compactly in `l` as
+/// left behind by the decomposition with pivot columns `q`.
+pub(crate) unsafe fn trsm_lower_compact<const ALIGN: usize>(
+    l: GF2MatWindow<'_, ALIGN>,
+    q: &[usize],
+    b: &mut GF2MatWindowMut<'_, ALIGN>,
+) {
+    let r = q.len();
+    debug_assert_eq!(l.nrows(), r);
+    debug_assert_eq!(b.nrows(), r);
+    let chunk_bits = ALIGN * U8SZ;
+    // everything of `l` left of the first pivot column is zero, drop it so
+    // the products below only span the columns which matter
+    let start = q.first().map_or(0, |&c| c / chunk_bits);
+    if start != 0 {
+        let (_, l) = l.split_chunk_cols(start);
+        let q: Vec<usize> = q.iter().map(|&c| c - start * chunk_bits).collect();
+        return unsafe { trsm_lower_compact(l, &q, b) };
+    }
+    if r <= COMPACT_TRSM_CUTOFF {
+        for t in 1..r {
+            for (s, &col) in q[..t].iter().enumerate() {
+                if unsafe { l.get_unchecked(t, col) } {
+                    let (dst, src) = unsafe { b.distinct_row_slices_mut_immut(t, s) };
+                    unsafe { xor_aligned_slices::<ALIGN>(dst, src) };
+                }
+            }
+        }
+        return;
+    }
+    // split at whole chunks where possible, so that full rank blocks can
+    // be multiplied without scattering
+    let h = match r / 2 {
+        h if h >= chunk_bits => h / chunk_bits * chunk_bits,
+        h => h,
+    };
+    let (l_top, l_bot) = l.split_rows(h);
+    let (mut b_top, mut b_bot) = b.reborrow().split_rows_mut(h);
+    unsafe { trsm_lower_compact(l_top, &q[..h], &mut b_top) };
+    let (l21, _) = l_bot.split_chunk_cols((q[h - 1] + 1).div_ceil(chunk_bits));
+    unsafe { addmul_scattered(&mut b_bot, &l21, &b_top, &q[..h]) };
+    unsafe { trsm_lower_compact(l_bot, &q[h..], &mut b_bot) };
+}
+
+unsafe fn ple_recurse<const ALIGN: usize>(
+    mat: &mut GF2MatWindowMut<'_, ALIGN>,
+    perm: &mut [usize],
+    stop_at_gap: bool,
+) -> Vec<usize> {
+    let chunk_bits = ALIGN * U8SZ;
+    let nchunks = mat.ncols().div_ceil(chunk_bits);
+    if mat.ncols() <= PLE_CUTOFF || nchunks < 2 {
+        return ple_m4ri(mat, perm, stop_at_gap);
+    }
+    if mat.nrows() == 0 {
+        return Vec::new();
+    }
+    let h = nchunks / 2;
+    let (mut a1, mut a2) = mat.reborrow().split_chunk_cols_mut(h);
+    let q1 = unsafe { ple_recurse(&mut a1, perm, stop_at_gap) };
+    let r1 = q1.len();
+    if stop_at_gap && r1 < a1.ncols() {
+        return q1;
+    }
+    apply_swaps(&mut a2, &perm[..r1]);
+
+    let (l1, mut m1) = a1.split_rows_mut(r1);
+    let (mut a12, mut a22) = a2.split_rows_mut(r1);
+    if r1 != 0 {
+        let l1 = unsafe { l1.get_window_unchecked::<ALIGN>(0, 0, r1, l1.nchunk_cols()) };
+        unsafe {
+            trsm_lower_compact(l1, &q1, &mut a12);
+            addmul_scattered(&mut a22, &m1, &a12, &q1);
+        }
+    }
+    let q2 = unsafe { ple_recurse(&mut a22, &mut perm[r1..], stop_at_gap) };
+    let r2 = q2.len();
+    apply_swaps(&mut m1, &perm[r1..r1 + r2]);
+    for p in &mut perm[r1..r1 + r2] {
+        *p += r1;
+    }
+    let n1 = h * chunk_bits;
+    q1.into_iter().chain(q2.into_iter().map(|q| q + n1)).collect()
+}
+
+/// The permutations and rank profile of a PLE or PLUQ decomposition.
+/// Permutations are stored as swaps like in LAPACK, `rows[i] = p` means row
+/// `i` was swapped with row `p`, done for every `i` in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LuPermutations {
+    /// The row swaps, applying them to the rows of `A` gives `P^-1 * A`
+    pub rows: Vec<usize>,
+    /// The column swaps, applying them to the columns of `A` gives
+    /// `A * Q^-1`. They do nothing for PLE.
+    pub cols: Vec<usize>,
+    /// The pivot columns of `E` in increasing order, its length is the rank
+    pub rank_profile: Vec<usize>,
+}
+
+impl LuPermutations {
+    pub fn rank(&self) -> usize {
+        self.rank_profile.len()
+    }
+}
+
+fn ple_impl<const ALIGN: usize, T>(mat: &mut T, stop_at_gap: bool) -> LuPermutations
+where
+    T: GF2MatLikeMut<ALIGN> + ?Sized,
+{
+    let mut rows: Vec<usize> = (0..mat.nrows()).collect();
+    let cols = (0..mat.ncols()).collect();
+    // recurse on WINDOW_ALIGN chunks, which is what the products are cut into
+    // anyway, so that the blocks can be split finely
+    let rank_profile = if !ALIGN.is_multiple_of(WINDOW_ALIGN) {
+        ple_m4ri(mat, &mut rows, stop_at_gap)
+    } else {
+        let nchunks = mat.nchunk_cols() * ALIGN / WINDOW_ALIGN;
+        let mut window =
+            unsafe { mat.get_window_mut_unchecked::<WINDOW_ALIGN>(0, 0, mat.nrows(), nchunks) };
+        unsafe { ple_recurse(&mut window, &mut rows, stop_at_gap) }
+    };
+    LuPermutations { rows, cols, rank_profile }
+}
+
+/// Computes `A = P * L * E` in place of `A`, see the module docs for how
+/// `L` and `E` are stored.
+pub fn ple<const ALIGN: usize, T>(mat: &mut T) -> LuPermutations
+where
+    T: GF2MatLikeMut<ALIGN> + ?Sized,
+{
+    ple_impl(mat, false)
+}
+
+/// Whether the columns of `mat` are linearly independent. Runs the PLE
+/// decomposition in place of `mat` but gives up at the first column
+/// without a pivot, leaving `mat` in some intermediate state.
+pub(crate) fn has_full_col_rank<const ALIGN: usize, T>(mat: &mut T) -> bool
+where
+    T: GF2MatLikeMut<ALIGN> + ?Sized,
+{
+    ple_impl(mat, true).rank() == mat.ncols()
+}
+
+/// Computes `A = P * L * U * Q` in place of `A`, with `L` unit lower
+/// triangular and `U` upper triangular with unit diagonal in its first
+/// `rank` columns. Both are stored packed like in LAPACK, `L` strictly below
+/// the diagonal of the first `rank` columns and `U` on and above it.
+///
+/// This is the PLE decomposition with its pivot columns swapped to the
+/// front, which moves the compactly stored `L` into place as well.
+pub fn pluq<const ALIGN: usize, T>(mat: &mut T) -> LuPermutations
+where
+    T: GF2MatLikeMut<ALIGN> + ?Sized,
+{
+    let mut perms = ple(mat);
+    for (t, &q) in perms.rank_profile.iter().enumerate() {
+        perms.cols[t] = q;
+    }
+    apply_col_swaps(mat, &perms.cols[..perms.rank()], false);
+    perms
+}
@@ -0,0 +1,222 @@
+/*!
+Polynomials over GF(2).
+
+Coefficients are packed into words like the rows of a matrix, the one of
+`x^i` being bit `i % 64` of word `i / 64`. There are never any trailing zero
+words, so the zero polynomial has no words at all and equality is equality
+of the words.
+*/
+
+use std::fmt::{Debug, Display, Formatter};
+use std::ops::{Add, Mul};
+
+use crate::gf2mat::*;
+use crate::mul::addmul;
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// A polynomial over GF(2)
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct GF2Poly {
+    words: Vec<u64>,
+}
+
+/// `dst ^= src * x^shift`, `dst` has to be long enough to hold the result
+fn xor_shifted(dst: &mut [u64], src: &[u64], shift: usize) {
+    let (w, b) = (shift / WORD_BITS, shift % WORD_BITS);
+    if b == 0 {
+        for (d, &s) in dst[w..].iter_mut().zip(src) {
+            *d ^= s;
+        }
+        return;
+    }
+    let mut carry = 0;
+    for (d, &s) in dst[w..].iter_mut().zip(src) {
+        *d ^= (s << b) | carry;
+        carry = s >> (WORD_BITS - b);
+    }
+    if carry != 0 {
+        dst[w + src.len()] ^= carry;
+    }
+}
+
+impl GF2Poly {
+    pub fn zero() -> Self {
+        Self::default()
+    }
+
+    pub fn one() -> Self {
+        Self::monomial(0)
+    }
+
+    /// `x^k`
+    pub fn monomial(k: usize) -> Self {
+        let mut words = vec![0; k / WORD_BITS + 1];
+        words[k / WORD_BITS] = 1 << (k % WORD_BITS);
+        Self { words }
+    }
+
+    /// The polynomial with the packed coefficients `words`, which may have
+    /// trailing zeros
+    pub fn from_words(mut words: Vec<u64>) -> Self {
+        while words.last() == Some(&0) {
+            words.pop();
+        }
+        Self { words }
+    }
+
+    /// The polynomial with coefficient `coeffs[i]` for `x^i`
+    pub fn from_coeffs(coeffs: &[bool]) -> Self {
+        let mut words = vec![0; coeffs.len().div_ceil(WORD_BITS)];
+        for (i, &c) in coeffs.iter().enumerate() {
+            words[i / WORD_BITS] |= (c as u64) << (i % WORD_BITS);
+        }
+        Self::from_words(words)
+    }
+
+    /// The packed coefficients, without trailing zero words
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    /// The degree, `None` for the zero polynomial
+    pub fn degree(&self) -> Option<usize> {
+        let last = self.words.last()?;
+        Some((self.words.len() - 1) * WORD_BITS + (WORD_BITS - 1 - last.leading_zeros() as usize))
+    }
+
+    /// The coefficient of `x^i`
+    pub fn coeff(&self, i: usize) -> bool {
+        self.words.get(i / WORD_BITS).is_some_and(|w| (w >> (i % WORD_BITS)) & 1 == 1)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    pub fn is_one(&self) -> bool {
+        self.words == [1]
+    }
+
+    /// Quotient and remainder of the division by `rhs`, panics if it is zero
+    pub fn div_rem(&self, rhs: &Self) -> (Self, Self) {
+        let d = rhs.degree().expect("division by the zero polynomial");
+        let mut rem = self.words.clone();
+        let Some(n) = self.degree().filter(|&n| n >= d) else {
+            return (Self::zero(), self.clone());
+        };
+        let mut quot = vec![0; (n - d) / WORD_BITS + 1];
+        for i in (d..=n).rev() {
+            if (rem[i / WORD_BITS] >> (i % WORD_BITS)) & 1 == 1 {
+                quot[(i - d) / WORD_BITS] |= 1 << ((i - d) % WORD_BITS);
+                xor_shifted(&mut rem, &rhs.words, i - d);
+            }
+        }
+        (Self::from_words(quot), Self::from_words(rem))
+    }
+
+    /// The monic greatest common divisor, zero only if both are zero
+    pub fn gcd(&self, rhs: &Self) -> Self {
+        let (mut a, mut b) = (self.clone(), rhs.clone());
+        while !b.is_zero() {
+            let r = a.div_rem(&b).1;
+            a = b;
+            b = r;
+        }
+        a
+    }
+
+    /// The least common multiple, zero if either is zero
+    pub fn lcm(&self, rhs: &Self) -> Self {
+        if self.is_zero() || rhs.is_zero() {
+            return Self::zero();
+        }
+        &self.div_rem(&self.gcd(rhs)).0 * rhs
+    }
+
+    /// Evaluates the polynomial at a square matrix by Horner's scheme
+    pub fn eval<const ALIGN: usize, S: GF2MatLike<ALIGN>>(&self, mat: &S) -> AlignedGF2Mat<ALIGN> {
+        let n = mat.nrows();
+        assert_eq!(n, mat.ncols(), "polynomials can only be evaluated at square matrices");
+        let mut res = AlignedGF2Mat::<ALIGN>::zero(n, n);
+        let Some(deg) = self.degree() else {
+            return res;
+        };
+        if n == 0 {
+            return res;
+        }
+        let mat = mat.to_mat();
+        let mut tmp = AlignedGF2Mat::<ALIGN>::zero(n, n);
+        for i in (0..=deg).rev() {
+            if i != deg {
+                tmp.clear();
+                unsafe { addmul(&mut tmp, &res, &mat) };
+                std::mem::swap(&mut res, &mut tmp);
+            }
+            if self.coeff(i) {
+                for j in 0..n {
+                    unsafe { res.flip_unchecked(j, j) };
+                }
+            }
+        }
+        res
+    }
+}
+
+impl Add for &GF2Poly {
+    type Output = GF2Poly;
+
+    fn add(self, rhs: &GF2Poly) -> GF2Poly {
+        let (long, short) = if self.words.len() >= rhs.words.len() { (self, rhs) } else { (rhs, self) };
+        let mut words = long.words.clone();
+        xor_shifted(&mut words, &short.words, 0);
+        GF2Poly::from_words(words)
+    }
+}
+
+impl Mul for &GF2Poly {
+    type Output = GF2Poly;
+
+    fn mul(self, rhs: &GF2Poly) -> GF2Poly {
+        if self.is_zero() || rhs.is_zero() {
+            return GF2Poly::zero();
+        }
+        let mut words = vec![0; self.words.len() + rhs.words.len()];
+        for (w, &word) in self.words.iter().enumerate() {
+            let mut bits = word;
+            while bits != 0 {
+                let b = bits.trailing_zeros() as usize;
+                xor_shifted(&mut words, &rhs.words, w * WORD_BITS + b);
+                bits &= bits - 1;
+            }
+        }
+        GF2Poly::from_words(words)
+    }
+}
+
+impl Display for GF2Poly {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let Some(deg) = self.degree() else {
+            return write!(f, "0");
+        };
+        let mut first = true;
+        for i in (0..=deg).rev().filter(|&i| self.coeff(i)) {
+            if !first {
+                write!(f, " + ")?;
+            }
+            first = false;
+            match i {
+                0 => write!(f, "1")?,
+                1 => write!(f, "x")?,
+                _ => write!(f, "x^{i}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Debug for GF2Poly {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
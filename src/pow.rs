@@ -0,0 +1,77 @@
+/*!
+Matrix powers by square-and-multiply.
+
+The exponent is walked from its most significant bit, squaring for every bit
+and multiplying by the base for every set one. All products go through
+`addmul` into one of two buffers which are swapped after each step, so no
+matter how large the exponent only three matrices are ever allocated.
+*/
+
+use std::borrow::Cow;
+use std::mem::swap;
+
+use crate::gf2mat::*;
+use crate::mul::addmul;
+
+/// Exponents accepted by `AlignedGF2Mat::pow`
+pub trait Exponent {
+    /// The exponent as big-endian bytes, leading zeros are allowed
+    fn be_bytes(&self) -> Cow<'_, [u8]>;
+}
+
+impl Exponent for u128 {
+    fn be_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(self.to_be_bytes().to_vec())
+    }
+}
+
+impl Exponent for &[u8] {
+    fn be_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl<const N: usize> Exponent for [u8; N] {
+    fn be_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl<const ALIGN: usize> AlignedGF2Mat<ALIGN> {
+    /// Raises the matrix to the power `e`, which is either a `u128` or a
+    /// big-endian byte string of any length. Panics if the matrix is not
+    /// square.
+    pub fn pow<E: Exponent>(&self, e: E) -> Self {
+        let n = self.nrows();
+        assert_eq!(n, self.ncols(), "only square matrices have powers");
+        let bytes = e.be_bytes();
+        let mut bits = bytes
+            .iter()
+            .flat_map(|&byte| (0..u8::BITS).rev().map(move |i| (byte >> i) & 1 == 1))
+            .skip_while(|&bit| !bit);
+        // the leading one, which saves squaring the identity
+        if bits.next().is_none() {
+            return Self::identity(n);
+        }
+        if n == 0 {
+            return Self::zero(0, 0);
+        }
+        let mut res = self.clone();
+        let mut tmp = Self::zero(n, n);
+        for bit in bits {
+            tmp.clear();
+            unsafe { addmul(&mut tmp, &res, &res) };
+            swap(&mut res, &mut tmp);
+            if bit {
+                tmp.clear();
+                unsafe { addmul(&mut tmp, &res, self) };
+                swap(&mut res, &mut tmp);
+            }
+            // nilpotent, it stays zero from here on
+            if res.is_zero() {
+                break;
+            }
+        }
+        res
+    }
+}
@@ -0,0 +1,113 @@
+/*!
+Solving linear systems `A * X = B` and `X * A = B`.
+
+`A` is decomposed as `P * L * U * Q` with `pluq`. With `r` its rank, the
+first `r` rows of `P^-1 * B` are solved against the top of `L`, and the
+remaining ones have to be cancelled by the bottom of `L`, otherwise there is
+no solution. The free variables, those past the first `r` columns of `U`, are
+set to zero, which leaves one more triangular system with the top left block
+of `U`. Both solves and the consistency check do their work with `addmul`.
+*/
+
+use crate::gf2mat::*;
+use crate::mul::{addmul, WINDOW_ALIGN};
+use crate::ple::{apply_swaps, pluq, trsm_lower_compact, LuPermutations};
+use crate::trsm::{trsm_unchecked, Variant};
+
+/// Overwrites `b`, which holds `P^-1 * B`, with `Q * X` in its first `rank`
+/// rows for `lu` as left behind by `pluq`.
+unsafe fn solve_packed<const ALIGN: usize>(
+    lu: GF2MatWindow<'_, ALIGN>,
+    perms: &LuPermutations,
+    b: &mut GF2MatWindowMut<'_, ALIGN>,
+) -> Result<(), SolveError> {
+    let r = perms.rank();
+    let chunk_bits = ALIGN * u8::BITS as usize;
+    let rchunks = r.div_ceil(chunk_bits);
+    let (lu_top, lu_bot) = lu.split_rows(r);
+    let (mut b_top, mut b_bot) = b.reborrow().split_rows_mut(r);
+
+    let q: Vec<usize> = (0..r).collect();
+    unsafe { trsm_lower_compact(lu_top, &q, &mut b_top) };
+    if b_bot.nrows() != 0 {
+        // the rows of `U` past the rank are zero, so the bottom of `lu` is
+        // just `L` within the first `r` columns
+        let (l_bot, _) = lu_bot.split_chunk_cols(rchunks);
+        unsafe { addmul(&mut b_bot, &l_bot, &b_top) };
+        if !b_bot.is_zero() {
+            return Err(SolveError::Inconsistent);
+        }
+    }
+
+    // the top right of `U` belongs to the free variables, copy out the
+    // square part so that nothing right of it gets multiplied in
+    let mut u = AlignedGF2Mat::<ALIGN>::zero(r, r);
+    let (u_src, _) = lu_top.split_chunk_cols(rchunks);
+    u.copy_from(&u_src);
+    u.clear_padding();
+    unsafe { trsm_unchecked(Variant::UpperLeft, &u, &mut b_top) };
+    Ok(())
+}
+
+/// Solves `a * X = b`, returning one solution if there are several. Fails
+/// with `SolveError::Inconsistent` if there is none.
+pub fn solve<const ALIGN: usize, S1, S2>(a: &S1, b: &S2) -> Result<AlignedGF2Mat<ALIGN>, SolveError>
+where
+    S1: GF2MatLike<ALIGN>,
+    S2: GF2MatLike<ALIGN>,
+{
+    let (m, n, k) = (a.nrows(), a.ncols(), b.ncols());
+    if b.nrows() != m {
+        return Err(SolveError::DimMismatch(m, n, b.nrows(), k));
+    }
+    let mut x = AlignedGF2Mat::<ALIGN>::zero(n, k);
+    if m == 0 || k == 0 {
+        return Ok(x);
+    }
+    if n == 0 {
+        return if b.is_zero() { Ok(x) } else { Err(SolveError::Inconsistent) };
+    }
+
+    let mut lu = a.to_mat();
+    let perms = pluq(&mut lu);
+    let r = perms.rank();
+    let mut pb = b.to_mat();
+    apply_swaps(&mut pb, &perms.rows);
+    if r == 0 {
+        return if pb.is_zero() { Ok(x) } else { Err(SolveError::Inconsistent) };
+    }
+
+    // work on WINDOW_ALIGN chunks when we can, so the recursion splits finely
+    if ALIGN.is_multiple_of(WINDOW_ALIGN) {
+        let lu_chunks = lu.nchunk_cols() * ALIGN / WINDOW_ALIGN;
+        let b_chunks = pb.nchunk_cols() * ALIGN / WINDOW_ALIGN;
+        let lu_w = unsafe { lu.get_window_unchecked::<WINDOW_ALIGN>(0, 0, m, lu_chunks) };
+        let mut b_w = unsafe { pb.get_window_mut_unchecked::<WINDOW_ALIGN>(0, 0, m, b_chunks) };
+        unsafe { solve_packed(lu_w, &perms, &mut b_w) }?;
+    } else {
+        let lu_w = unsafe { lu.get_window_unchecked::<ALIGN>(0, 0, m, lu.nchunk_cols()) };
+        let mut b_w = unsafe { pb.get_window_mut_unchecked::<ALIGN>(0, 0, m, pb.nchunk_cols()) };
+        unsafe { solve_packed(lu_w, &perms, &mut b_w) }?;
+    }
+
+    // the free variables stay zero, and `X` is `Q^-1` applied to the rest
+    for t in 0..r {
+        unsafe { x.row_slice_mut_unchecked(t) }.copy_from_slice(unsafe { pb.row_slice_unchecked(t) });
+    }
+    for (t, &q) in perms.cols[..r].iter().enumerate().rev() {
+        unsafe { x.swap_rows_unchecked(t, q) };
+    }
+    Ok(x)
+}
+
+/// Solves `X * a = b` by solving the transposed system with `solve`
+pub fn solve_left<const ALIGN: usize, S1, S2>(a: &S1, b: &S2) -> Result<AlignedGF2Mat<ALIGN>, SolveError>
+where
+    S1: GF2MatLike<ALIGN>,
+    S2: GF2MatLike<ALIGN>,
+{
+    if b.ncols() != a.ncols() {
+        return Err(SolveError::DimMismatch(a.nrows(), a.ncols(), b.nrows(), b.ncols()));
+    }
+    Ok(solve(&a.transpose(), &b.transpose())?.transpose())
+}
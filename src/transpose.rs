@@ -0,0 +1,148 @@
+/*!
+Bit-matrix transposition.
+
+The matrix is handled as a grid of 64x64 blocks, each of which is loaded
+into 64 words and transposed in registers by swapping successively smaller
+sub-blocks (32x32 down to 1x1, the last three levels being the usual 8x8
+byte transpose). To keep both the rows we read and the rows we write in
+cache the block grid is split recursively until a tile fits.
+*/
+
+use crate::gf2mat::*;
+
+const WORD_SZ: usize = u64::BITS as usize;
+const WORD_BYTES: usize = WORD_SZ / u8::BITS as usize;
+/// Tiles of at most this many blocks per side are transposed directly,
+/// 8x8 blocks touch 512 rows of 64 bytes on either side.
+const TILE_BLOCKS: usize = 8;
+
+type Block = [u64; WORD_SZ];
+
+/// Transposes a 64x64 block where bit `c` of `blk[r]` is entry `(r, c)`
+fn transpose_block(blk: &mut Block) {
+    let mut j = WORD_SZ / 2;
+    let mut mask: u64 = u64::MAX >> j;
+    while j != 0 {
+        let mut k = 0;
+        while k < WORD_SZ {
+            let t = ((blk[k] >> j) ^ blk[k + j]) & mask;
+            blk[k] ^= t << j;
+            blk[k + j] ^= t;
+            k = (k + j + 1) & !j;
+        }
+        j >>= 1;
+        mask ^= mask << j;
+    }
+}
+
+fn word_range(row: &[u8], word: usize) -> std::ops::Range<usize> {
+    let start = word * WORD_BYTES;
+    start..(start + WORD_BYTES).min(row.len())
+}
+
+/// Loads the block at block-row `bi` and block-column `bj`, rows past the
+/// end of the matrix are read as zero
+unsafe fn load_block<const ALIGN: usize, S>(src: &S, bi: usize, bj: usize) -> Block
+where
+    S: GF2MatLike<ALIGN> + ?Sized,
+{
+    let mut blk = [0u64; WORD_SZ];
+    let nrows = (src.nrows() - bi * WORD_SZ).min(WORD_SZ);
+    for (r, word) in blk.iter_mut().enumerate().take(nrows) {
+        let row = unsafe { src.row_slice_unchecked(bi * WORD_SZ + r) };
+        *word = load_word(&row[word_range(row, bj)]);
+    }
+    blk
+}
+
+unsafe fn store_block<const ALIGN: usize, T>(dst: &mut T, bi: usize, bj: usize, blk: &Block)
+where
+    T: GF2MatLikeMut<ALIGN> + ?Sized,
+{
+    let nrows = (dst.nrows() - bi * WORD_SZ).min(WORD_SZ);
+    for (r, &word) in blk.iter().enumerate().take(nrows) {
+        let row = unsafe { dst.row_slice_mut_unchecked(bi * WORD_SZ + r) };
+        let range = word_range(row, bj);
+        store_word(&mut row[range], word);
+    }
+}
+
+/// Transposes the blocks `[bi0, bi1) x [bj0, bj1)` of `src` into `dst`
+unsafe fn transpose_tile<const ALIGN: usize, T, S>(
+    dst: &mut T,
+    src: &S,
+    (bi0, bi1): (usize, usize),
+    (bj0, bj1): (usize, usize),
+) where
+    T: GF2MatLikeMut<ALIGN> + ?Sized,
+    S: GF2MatLike<ALIGN> + ?Sized,
+{
+    let (ni, nj) = (bi1 - bi0, bj1 - bj0);
+    if ni > TILE_BLOCKS || nj > TILE_BLOCKS {
+        if ni >= nj {
+            let mid = bi0 + ni / 2;
+            unsafe {
+                transpose_tile(dst, src, (bi0, mid), (bj0, bj1));
+                transpose_tile(dst, src, (mid, bi1), (bj0, bj1));
+            }
+        } else {
+            let mid = bj0 + nj / 2;
+            unsafe {
+                transpose_tile(dst, src, (bi0, bi1), (bj0, mid));
+                transpose_tile(dst, src, (bi0, bi1), (mid, bj1));
+            }
+        }
+        return;
+    }
+    for bi in bi0..bi1 {
+        for bj in bj0..bj1 {
+            let mut blk = unsafe { load_block(src, bi, bj) };
+            transpose_block(&mut blk);
+            unsafe { store_block(dst, bj, bi, &blk) };
+        }
+    }
+}
+
+/// Writes the transpose of `src` into `dst`, which has to have `src.ncols()`
+/// rows and `src.nrows()` columns. Every word holding its columns is
+/// overwritten, so `dst` need not be zeroed.
+pub(crate) fn transpose_into<const ALIGN: usize, T, S>(dst: &mut T, src: &S)
+where
+    T: GF2MatLikeMut<ALIGN> + ?Sized,
+    S: GF2MatLike<ALIGN> + ?Sized,
+{
+    assert_eq!(dst.nrows(), src.ncols());
+    assert_eq!(dst.ncols(), src.nrows());
+    let nbi = src.nrows().div_ceil(WORD_SZ);
+    let nbj = src.ncols().div_ceil(WORD_SZ);
+    unsafe { transpose_tile(dst, src, (0, nbi), (0, nbj)) };
+}
+
+/// Transposes a square matrix in place by swapping mirrored blocks
+pub(crate) fn transpose_square_in_place<const ALIGN: usize, T>(mat: &mut T)
+where
+    T: GF2MatLikeMut<ALIGN> + ?Sized,
+{
+    assert_eq!(
+        mat.nrows(),
+        mat.ncols(),
+        "only square matrices can be transposed in place"
+    );
+    let nb = mat.nrows().div_ceil(WORD_SZ);
+    for ti in (0..nb).step_by(TILE_BLOCKS) {
+        for tj in (ti..nb).step_by(TILE_BLOCKS) {
+            for bi in ti..(ti + TILE_BLOCKS).min(nb) {
+                for bj in tj.max(bi)..(tj + TILE_BLOCKS).min(nb) {
+                    let mut upper = unsafe { load_block(mat, bi, bj) };
+                    let mut lower = unsafe { load_block(mat, bj, bi) };
+                    transpose_block(&mut upper);
+                    transpose_block(&mut lower);
+                    unsafe {
+                        store_block(mat, bj, bi, &upper);
+                        store_block(mat, bi, bj, &lower);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,292 @@
+/*!
+Triangular solves with many right hand sides (TRSM).
+
+`trsm_upper_left` solves `U * X = B`, `trsm_lower_left` solves `L * X = B`,
+`trsm_upper_right` solves `X * U = B` and `trsm_lower_right` solves
+`X * L = B`, all in place of `B`. Over GF(2) a triangular matrix is invertible
+exactly when its diagonal is all ones, so with `Diag::NonUnit` the diagonal is
+only checked before solving as for `Diag::Unit`, which never reads it. Only
+the triangle itself is read, so `L` and `U` may share a matrix like they do
+after `pluq`.
+
+Large systems are split in two at a chunk boundary. One half is solved, the
+other one is updated with `addmul` and then solved in turn. Small ones are
+solved `MAX_TBL_SZ` unknowns at a time: for a left solve the rows of a block
+are solved directly and then cleared from all remaining rows with the tables
+of `tabulate_m4rm`. For a right solve a single table row added to each row
+of `B` both solves the columns of a block and updates the remaining ones,
+the table being built from the matching rows of the triangular matrix.
+*/
+
+use crate::gf2mat::*;
+use crate::m4rm::{tabulate_m4rm, GRAY_TBL, MAX_TBL_SZ};
+use crate::mul::{addmul, WINDOW_ALIGN};
+
+const U8SZ: usize = u8::BITS as usize;
+/// Systems with at most this many unknowns are solved by the Four-Russians kernels
+const TRSM_CUTOFF: usize = 1024;
+
+/// Whether the diagonal of a triangular matrix is read or taken to be all ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Diag {
+    Unit,
+    NonUnit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Variant {
+    UpperLeft,
+    LowerLeft,
+    UpperRight,
+    LowerRight,
+}
+
+/// Four-Russians kernel for `U * X = B` and `L * X = B`
+unsafe fn trsm_left_m4r<const ALIGN: usize>(
+    upper: bool,
+    tri: GF2MatWindow<'_, ALIGN>,
+    b: &mut GF2MatWindowMut<'_, ALIGN>,
+) {
+    let r = tri.nrows();
+    let mut tbl = AlignedGF2Mat::<ALIGN>::zero(1 << MAX_TBL_SZ, b.max_ncols());
+    let nblocks = r.div_ceil(MAX_TBL_SZ);
+    for blk in 0..nblocks {
+        // back substitution for U, forward substitution for L
+        let i0 = if upper { nblocks - 1 - blk } else { blk } * MAX_TBL_SZ;
+        let k = MAX_TBL_SZ.min(r - i0);
+        for dt in 0..k {
+            let t = if upper { i0 + k - 1 - dt } else { i0 + dt };
+            let deps = if upper { t + 1..i0 + k } else { i0..t };
+            for s in deps {
+                if unsafe { tri.get_unchecked(t, s) } {
+                    let (dst, src) = unsafe { b.distinct_row_slices_mut_immut(t, s) };
+                    unsafe { xor_aligned_slices::<ALIGN>(dst, src) };
+                }
+            }
+        }
+        unsafe { tabulate_m4rm(&mut tbl, &*b, i0, k) };
+        let rest = if upper { 0..i0 } else { i0 + k..r };
+        for t in rest {
+            let s = read_bits(unsafe { tri.row_slice_unchecked(t) }, i0, k);
+            if s != 0 {
+                unsafe { b.add_row_from_mat(&tbl, GRAY_TBL[s as usize] as usize, t) };
+            }
+        }
+    }
+}
+
+/// Four-Russians kernel for `X * U = B` and `X * L = B`
+unsafe fn trsm_right_m4r<const ALIGN: usize>(
+    upper: bool,
+    tri: GF2MatWindow<'_, ALIGN>,
+    b: &mut GF2MatWindowMut<'_, ALIGN>,
+) {
+    let r = tri.nrows();
+    let chunk_bits = ALIGN * U8SZ;
+    let mut tbl = AlignedGF2Mat::<ALIGN>::zero(1 << MAX_TBL_SZ, b.max_ncols());
+    let mut piv = AlignedGF2Mat::<ALIGN>::zero(MAX_TBL_SZ, b.max_ncols());
+    let mut sol = [0u64; 1 << MAX_TBL_SZ];
+    let nblocks = r.div_ceil(MAX_TBL_SZ);
+    for blk in 0..nblocks {
+        // the unknowns of U depend on those before them, for L on those after
+        let c0 = if upper { blk } else { nblocks - 1 - blk } * MAX_TBL_SZ;
+        let k = MAX_TBL_SZ.min(r - c0);
+        let order = |dj: usize| if upper { dj } else { k - 1 - dj };
+
+        // sol[s] solves the block for the bits s of a row of B
+        let mut deps = [0u64; MAX_TBL_SZ];
+        for (j, dep) in deps.iter_mut().enumerate().take(k) {
+            for i in 0..k {
+                let before = if upper { i < j } else { i > j };
+                if before && unsafe { tri.get_unchecked(c0 + i, c0 + j) } {
+                    *dep |= 1 << i;
+                }
+            }
+        }
+        for (s, x) in sol.iter_mut().enumerate().take(1 << k) {
+            *x = 0;
+            for j in (0..k).map(order) {
+                let bit = ((s as u64 >> j) ^ (*x & deps[j]).count_ones() as u64) & 1;
+                *x |= bit << j;
+            }
+        }
+        // the table adds the solved unknowns times their rows of the
+        // triangle, diagonal excluded, which also turns the block of a row
+        // into its solution. Only the chunks right of the block for U, or
+        // left of it for L, can change.
+        let (first, nchunks) = if upper {
+            (c0 / chunk_bits, b.nchunk_cols() - c0 / chunk_bits)
+        } else {
+            (0, (c0 + k).div_ceil(chunk_bits))
+        };
+        let lc = c0 - first * chunk_bits;
+        let tri_part = unsafe { tri.get_window_unchecked::<ALIGN>(c0, first, k, nchunks) };
+        let mut piv_part = unsafe { piv.get_window_mut_unchecked::<ALIGN>(0, first, k, nchunks) };
+        let mut tbl_part = unsafe { tbl.get_window_mut_unchecked::<ALIGN>(0, first, 1 << k, nchunks) };
+        let mut b_part = unsafe { b.get_window_mut_unchecked::<ALIGN>(0, first, b.nrows(), nchunks) };
+        for t in 0..k {
+            let dst = unsafe { piv_part.row_slice_mut_unchecked(t) };
+            dst.copy_from_slice(unsafe { tri_part.row_slice_unchecked(t) });
+            if upper {
+                clear_prefix(dst, lc + t + 1);
+            } else {
+                clear_suffix(dst, lc + t);
+            }
+        }
+        unsafe { tabulate_m4rm(&mut tbl_part, &piv_part, 0, k) };
+        for i in 0..b_part.nrows() {
+            let s = read_bits(unsafe { b_part.row_slice_unchecked(i) }, lc, k);
+            if s != 0 {
+                let x = sol[s as usize] as usize;
+                unsafe { b_part.add_row_from_mat(&tbl_part, GRAY_TBL[x] as usize, i) };
+            }
+        }
+    }
+}
+
+unsafe fn trsm_recurse<const ALIGN: usize>(
+    variant: Variant,
+    tri: GF2MatWindow<'_, ALIGN>,
+    b: &mut GF2MatWindowMut<'_, ALIGN>,
+) {
+    let r = tri.nrows();
+    let chunk_bits = ALIGN * U8SZ;
+    if r <= TRSM_CUTOFF.max(chunk_bits) {
+        unsafe {
+            match variant {
+                Variant::UpperLeft => trsm_left_m4r(true, tri, b),
+                Variant::LowerLeft => trsm_left_m4r(false, tri, b),
+                Variant::UpperRight => trsm_right_m4r(true, tri, b),
+                Variant::LowerRight => trsm_right_m4r(false, tri, b),
+            }
+        }
+        return;
+    }
+    // split at a chunk so that all four blocks of the triangle are windows
+    let hc = (r / 2).div_ceil(chunk_bits);
+    let h = hc * chunk_bits;
+    let (top, bot) = tri.split_rows(h);
+    let (t11, t12) = top.split_chunk_cols(hc);
+    let (t21, t22) = bot.split_chunk_cols(hc);
+    unsafe {
+        match variant {
+            Variant::UpperLeft => {
+                let (mut b1, mut b2) = b.reborrow().split_rows_mut(h);
+                trsm_recurse(variant, t22, &mut b2);
+                addmul(&mut b1, &t12, &b2);
+                trsm_recurse(variant, t11, &mut b1);
+            }
+            Variant::LowerLeft => {
+                let (mut b1, mut b2) = b.reborrow().split_rows_mut(h);
+                trsm_recurse(variant, t11, &mut b1);
+                addmul(&mut b2, &t21, &b1);
+                trsm_recurse(variant, t22, &mut b2);
+            }
+            Variant::UpperRight => {
+                let (mut b1, mut b2) = b.reborrow().split_chunk_cols_mut(hc);
+                trsm_recurse(variant, t11, &mut b1);
+                addmul(&mut b2, &b1, &t12);
+                trsm_recurse(variant, t22, &mut b2);
+            }
+            Variant::LowerRight => {
+                let (mut b1, mut b2) = b.reborrow().split_chunk_cols_mut(hc);
+                trsm_recurse(variant, t22, &mut b2);
+                addmul(&mut b1, &b2, &t21);
+                trsm_recurse(variant, t11, &mut b1);
+            }
+        }
+    }
+}
+
+/// Solves in place of `b` with a unit diagonal, without checking dimensions.
+/// Goes through `WINDOW_ALIGN` windows when it can, so the recursion splits
+/// finely.
+///
+/// # Safety
+/// `tri` has to be square, and as tall as `b` for a left solve or as wide
+/// as it for a right solve.
+pub(crate) unsafe fn trsm_unchecked<const ALIGN: usize, S, T>(variant: Variant, tri: &S, b: &mut T)
+where
+    S: GF2MatLike<ALIGN> + ?Sized,
+    T: GF2MatLikeMut<ALIGN> + ?Sized,
+{
+    let (r, m) = (tri.nrows(), b.nrows());
+    if r == 0 || m == 0 || b.ncols() == 0 {
+        return;
+    }
+    if ALIGN.is_multiple_of(WINDOW_ALIGN) {
+        let (t_chunks, b_chunks) = (tri.nchunk_cols() * ALIGN / WINDOW_ALIGN, b.nchunk_cols() * ALIGN / WINDOW_ALIGN);
+        let tri = unsafe { tri.get_window_unchecked::<WINDOW_ALIGN>(0, 0, r, t_chunks) };
+        let mut b = unsafe { b.get_window_mut_unchecked::<WINDOW_ALIGN>(0, 0, m, b_chunks) };
+        unsafe { trsm_recurse(variant, tri, &mut b) };
+    } else {
+        let tri = unsafe { tri.get_window_unchecked::<ALIGN>(0, 0, r, tri.nchunk_cols()) };
+        let mut b = unsafe { b.get_window_mut_unchecked::<ALIGN>(0, 0, m, b.nchunk_cols()) };
+        unsafe { trsm_recurse(variant, tri, &mut b) };
+    }
+}
+
+fn check_trsm<const ALIGN: usize, S, T>(tri: &S, b: &T, left: bool, diag: Diag) -> Result<(), TrsmError>
+where
+    S: GF2MatLike<ALIGN> + ?Sized,
+    T: GF2MatLike<ALIGN> + ?Sized,
+{
+    let r = tri.nrows();
+    if tri.ncols() != r {
+        return Err(TrsmError::NotSquare(r, tri.ncols()));
+    }
+    let shared = if left { b.nrows() } else { b.ncols() };
+    if shared != r {
+        return Err(TrsmError::DimMismatch(r, b.nrows(), b.ncols()));
+    }
+    if diag == Diag::NonUnit {
+        if let Some(i) = (0..r).find(|&i| !unsafe { tri.get_unchecked(i, i) }) {
+            return Err(TrsmError::Singular(i));
+        }
+    }
+    Ok(())
+}
+
+/// Solves `u * X = b` in place of `b`, for upper triangular `u`
+pub fn trsm_upper_left<const ALIGN: usize, S, T>(u: &S, b: &mut T, diag: Diag) -> Result<(), TrsmError>
+where
+    S: GF2MatLike<ALIGN> + ?Sized,
+    T: GF2MatLikeMut<ALIGN> + ?Sized,
+{
+    check_trsm(u, b, true, diag)?;
+    unsafe { trsm_unchecked(Variant::UpperLeft, u, b) };
+    Ok(())
+}
+
+/// Solves `l * X = b` in place of `b`, for lower triangular `l`
+pub fn trsm_lower_left<const ALIGN: usize, S, T>(l: &S, b: &mut T, diag: Diag) -> Result<(), TrsmError>
+where
+    S: GF2MatLike<ALIGN> + ?Sized,
+    T: GF2MatLikeMut<ALIGN> + ?Sized,
+{
+    check_trsm(l, b, true, diag)?;
+    unsafe { trsm_unchecked(Variant::LowerLeft, l, b) };
+    Ok(())
+}
+
+/// Solves `X * u = b` in place of `b`, for upper triangular `u`
+pub fn trsm_upper_right<const ALIGN: usize, S, T>(u: &S, b: &mut T, diag: Diag) -> Result<(), TrsmError>
+where
+    S: GF2MatLike<ALIGN> + ?Sized,
+    T: GF2MatLikeMut<ALIGN> + ?Sized,
+{
+    check_trsm(u, b, false, diag)?;
+    unsafe { trsm_unchecked(Variant::UpperRight, u, b) };
+    Ok(())
+}
+
+/// Solves `X * l = b` in place of `b`, for lower triangular `l`
+pub fn trsm_lower_right<const ALIGN: usize, S, T>(l: &S, b: &mut T, diag: Diag) -> Result<(), TrsmError>
+where
+    S: GF2MatLike<ALIGN> + ?Sized,
+    T: GF2MatLikeMut<ALIGN> + ?Sized,
+{
+    check_trsm(l, b, false, diag)?;
+    unsafe { trsm_unchecked(Variant::LowerRight, l, b) };
+    Ok(())
+}
@@ -0,0 +1,238 @@
+/*!
+Tuning `addmul` to the machine it runs on.
+
+How large a product has to be before recursing beats M4RM, how finely the
+decompositions cut their blocks, and which decompositions the planner tries
+first all depend on the caches of the host. A [`TuningProfile`] holds these,
+and [`TuningProfile::autotune`] finds them by timing products of a few sizes
+with every candidate, one setting at a time with the others fixed.
+
+`addmul` uses the current profile, which is the one last installed, else the
+one saved at the path in the `GF2MUL_PROFILE` environment variable when that
+is first needed, else the defaults the crate was tuned with. A profile which
+can't be loaded from there also gives the defaults, [`TuningProfile::init`]
+loads it up front and returns why it can't be.
+*/
+
+use std::path::Path;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::gf2mat::*;
+use crate::mul::{addmul_with_profile, AddMulAlgo, STRASSEN_CUTOFF, WINDOW_ALIGN};
+
+const U8SZ: usize = u8::BITS as usize;
+
+/// The window alignments in bytes a profile can use
+pub const WINDOW_ALIGNS: [usize; 3] = [16, 32, 64];
+
+/// The environment variable with the path of the profile to start with
+const PROFILE_ENV: &str = "GF2MUL_PROFILE";
+
+static PROFILE: OnceLock<RwLock<Arc<TuningProfile>>> = OnceLock::new();
+
+/// The parameters `addmul` is tuned by
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TuningProfile {
+    /// Products whose smallest dimension is below this are done by M4RM
+    /// alone, and the decompositions recurse until they are
+    pub strassen_cutoff: usize,
+    /// The alignment in bytes of the blocks the decompositions cut, one of
+    /// [`WINDOW_ALIGNS`]
+    pub window_align: usize,
+    /// The decompositions the planner considers, a decomposition is only
+    /// ever followed by itself or those after it
+    pub decomp_order: Vec<AddMulAlgo>,
+}
+
+impl Default for TuningProfile {
+    fn default() -> Self {
+        TuningProfile {
+            strassen_cutoff: STRASSEN_CUTOFF,
+            window_align: WINDOW_ALIGN,
+            decomp_order: vec![
+                #[cfg(feature = "decomp3x3")]
+                AddMulAlgo::Decomp3x3,
+                #[cfg(feature = "decomp5x5")]
+                AddMulAlgo::Decomp5x5,
+                AddMulAlgo::Decomp2x2,
+                AddMulAlgo::Decomp4x4,
+            ],
+        }
+    }
+}
+
+impl TuningProfile {
+    /// The profile `addmul` currently uses
+    pub fn current() -> Arc<Self> {
+        Self::global().read().unwrap().clone()
+    }
+
+    /// Makes `addmul` use this profile from now on
+    pub fn install(self) -> Result<(), ProfileError> {
+        self.install_in(Self::global())
+    }
+
+    /// Installs the profile at the path in `GF2MUL_PROFILE`, if it is set.
+    /// Unlike when `addmul` loads it by itself, a profile which can't be
+    /// loaded is an error rather than leaving the current one in place.
+    pub fn init() -> Result<(), ProfileError> {
+        Self::init_in(Self::global(), std::env::var_os(PROFILE_ENV))
+    }
+
+    /// [`TuningProfile::install`] into `slot` rather than the global profile
+    pub(crate) fn install_in(self, slot: &RwLock<Arc<Self>>) -> Result<(), ProfileError> {
+        self.validate()?;
+        *slot.write().unwrap() = Arc::new(self);
+        Ok(())
+    }
+
+    /// [`TuningProfile::init`] into `slot`, from `path` rather than the
+    /// environment
+    pub(crate) fn init_in<P: AsRef<Path>>(slot: &RwLock<Arc<Self>>, path: Option<P>) -> Result<(), ProfileError> {
+        match path {
+            Some(path) => Self::load(path)?.install_in(slot),
+            None => Ok(()),
+        }
+    }
+
+    /// Reads a profile saved by [`TuningProfile::save`]
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ProfileError> {
+        let profile: Self = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    /// Writes the profile as JSON
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ProfileError> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// The profile at the path in `GF2MUL_PROFILE`, if it is set
+    pub fn from_env() -> Result<Option<Self>, ProfileError> {
+        match std::env::var_os(PROFILE_ENV) {
+            Some(path) => Self::load(path).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Checks the profile is one `addmul` can use
+    pub fn validate(&self) -> Result<(), ProfileError> {
+        if !WINDOW_ALIGNS.contains(&self.window_align) {
+            return Err(ProfileError::WindowAlign(self.window_align, WINDOW_ALIGNS));
+        }
+        let chunk_bits = self.window_align * U8SZ;
+        if self.strassen_cutoff < 2 * chunk_bits {
+            return Err(ProfileError::Cutoff(self.strassen_cutoff, chunk_bits));
+        }
+        let order = &self.decomp_order;
+        let repeats = order.iter().enumerate().any(|(i, algo)| order[..i].contains(algo));
+        if repeats || order.iter().any(|algo| *algo == AddMulAlgo::M4RM || !algo.is_compiled()) {
+            return Err(ProfileError::DecompOrder(order.clone()));
+        }
+        Ok(())
+    }
+
+    /// Times `addmul` on this machine and returns the fastest profile among
+    /// the candidates in `opts`
+    pub fn autotune(opts: &TuneOptions) -> Self {
+        let mut rng = rand::thread_rng();
+        let operands: Vec<_> = opts.sizes.iter()
+            .map(|&n| (GF2Mat::random(n, n, &mut rng), GF2Mat::random(n, n, &mut rng)))
+            .collect();
+        let time = |profile: &TuningProfile| -> Duration {
+            operands.iter().map(|(lhs, rhs)| {
+                let mut prod = GF2Mat::zero(lhs.nrows(), rhs.ncols());
+                (0..opts.reps.max(1)).map(|_| {
+                    let start = Instant::now();
+                    unsafe { addmul_with_profile(&mut prod, lhs, rhs, opts.threads, profile) };
+                    start.elapsed()
+                }).min().unwrap()
+            }).sum()
+        };
+
+        let mut best = TuningProfile::default();
+        let mut best_time = time(&best);
+        let mut try_candidate = |best: &mut TuningProfile, candidate: TuningProfile| {
+            if candidate.validate().is_err() || candidate == *best {
+                return;
+            }
+            let elapsed = time(&candidate);
+            if elapsed < best_time {
+                best_time = elapsed;
+                *best = candidate;
+            }
+        };
+        for &window_align in &opts.window_aligns {
+            let candidate = TuningProfile { window_align, ..best.clone() };
+            try_candidate(&mut best, candidate);
+        }
+        for &strassen_cutoff in &opts.cutoffs {
+            let candidate = TuningProfile { strassen_cutoff, ..best.clone() };
+            try_candidate(&mut best, candidate);
+        }
+        for decomp_order in &opts.orders {
+            let candidate = TuningProfile { decomp_order: decomp_order.clone(), ..best.clone() };
+            try_candidate(&mut best, candidate);
+        }
+        best
+    }
+
+    fn global() -> &'static RwLock<Arc<TuningProfile>> {
+        // a profile which can't be loaded is no reason to fail a product,
+        // `init` is there to find out why
+        PROFILE.get_or_init(|| RwLock::new(Arc::new(Self::from_env().ok().flatten().unwrap_or_default())))
+    }
+}
+
+/// What [`TuningProfile::autotune`] times and which candidates it tries
+#[derive(Debug, Clone)]
+pub struct TuneOptions {
+    /// The sizes of the square products timed, each candidate is judged by
+    /// the sum of its times
+    pub sizes: Vec<usize>,
+    /// Each product is timed this many times, keeping the fastest
+    pub reps: usize,
+    /// The Strassen cutoffs tried, see [`TuningProfile::strassen_cutoff`]
+    pub cutoffs: Vec<usize>,
+    /// The window alignments tried, those not in [`WINDOW_ALIGNS`] are
+    /// skipped
+    pub window_aligns: Vec<usize>,
+    /// The decomposition orders tried, see [`TuningProfile::decomp_order`]
+    pub orders: Vec<Vec<AddMulAlgo>>,
+    /// The number of threads the products are timed on
+    pub threads: usize,
+}
+
+impl Default for TuneOptions {
+    fn default() -> Self {
+        let decomps = TuningProfile::default().decomp_order;
+        TuneOptions {
+            sizes: vec![4096, 6144, 8192],
+            reps: 3,
+            cutoffs: vec![2048, 3072, 4096, 6144, 8192],
+            window_aligns: WINDOW_ALIGNS.to_vec(),
+            orders: permutations(&decomps),
+            threads: 1,
+        }
+    }
+}
+
+fn permutations(algos: &[AddMulAlgo]) -> Vec<Vec<AddMulAlgo>> {
+    if algos.is_empty() {
+        return vec![Vec::new()];
+    }
+    let mut res = Vec::new();
+    for (i, &first) in algos.iter().enumerate() {
+        let mut rest = algos.to_vec();
+        rest.remove(i);
+        for mut perm in permutations(&rest) {
+            perm.insert(0, first);
+            res.push(perm);
+        }
+    }
+    res
+}
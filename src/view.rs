@@ -0,0 +1,220 @@
+/*!
+Bit-granular views into matrices.
+
+Windows can only start at `ALIGN`-byte column boundaries, since the
+kernels assume every row starts on a chunk. A view cut at an arbitrary
+column instead falls back to a copy which is shifted so that it starts
+at column zero. For mutable views the copy is written back into the
+matrix it came from when the view is dropped.
+*/
+
+use std::ops::{Bound, Range, RangeBounds};
+
+use crate::gf2mat::*;
+
+const U8SZ: usize = u8::BITS as usize;
+const WORD_SZ: usize = u64::BITS as usize;
+
+fn resolve_range<R: RangeBounds<usize>>(range: R, len: usize) -> Range<usize> {
+    let start = match range.start_bound() {
+        Bound::Included(&x) => x,
+        Bound::Excluded(&x) => x + 1,
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&x) => x + 1,
+        Bound::Excluded(&x) => x,
+        Bound::Unbounded => len,
+    };
+    if start > end || end > len {
+        panic!("{}", MatAccessError::RangeOutOfBounds(start, end, len));
+    }
+    start..end
+}
+
+/// Whether a view of `cols` can borrow the data of a matrix with `ncols` columns.
+/// The end of the view either has to be aligned or coincide with the end of the
+/// matrix, since otherwise the window would pick up the columns following it.
+fn is_aligned<const ALIGN: usize>(cols: &Range<usize>, ncols: usize) -> bool {
+    let chunk_bits = ALIGN * U8SZ;
+    cols.start.is_multiple_of(chunk_bits) && (cols.end.is_multiple_of(chunk_bits) || cols.end == ncols)
+}
+
+/// The chunk columns covering `cols`, as (first chunk, number of chunks)
+fn covering_chunks<const ALIGN: usize>(cols: &Range<usize>) -> (usize, usize) {
+    let chunk_bits = ALIGN * U8SZ;
+    let first = cols.start / chunk_bits;
+    (first, cols.end.div_ceil(chunk_bits) - first)
+}
+
+/// Copies `len` bits starting at `src_pos` of `src` to `dst_pos` of `dst`
+fn copy_bits(dst: &mut [u8], dst_pos: usize, src: &[u8], src_pos: usize, len: usize) {
+    for off in (0..len).step_by(WORD_SZ) {
+        let n = (len - off).min(WORD_SZ);
+        write_bits(dst, dst_pos + off, n, read_bits(src, src_pos + off, n));
+    }
+}
+
+/// A read-only view into part of a matrix, see `GF2MatLike::view`
+pub enum GF2MatView<'a, const ALIGN: usize> {
+    Window(GF2MatWindow<'a, ALIGN>),
+    Copy(AlignedGF2Mat<ALIGN>),
+}
+
+impl<'a, const ALIGN: usize> GF2MatView<'a, ALIGN> {
+    pub(crate) fn new<M, R, C>(mat: &'a M, rows: R, cols: C) -> Self
+    where
+        M: GF2MatLike<ALIGN> + ?Sized,
+        R: RangeBounds<usize>,
+        C: RangeBounds<usize>,
+    {
+        let rows = resolve_range(rows, mat.nrows());
+        let cols = resolve_range(cols, mat.ncols());
+        let (first_chunk, nchunks) = covering_chunks::<ALIGN>(&cols);
+        if is_aligned::<ALIGN>(&cols, mat.ncols()) {
+            return GF2MatView::Window(unsafe {
+                mat.get_window_unchecked(rows.start, first_chunk, rows.len(), nchunks)
+            });
+        }
+        let mut copy = AlignedGF2Mat::zero(rows.len(), cols.len());
+        for (i, src_row) in rows.enumerate() {
+            let src = unsafe { mat.row_slice_unchecked(src_row) };
+            let dst = unsafe { copy.row_slice_mut_unchecked(i) };
+            copy_bits(dst, 0, src, cols.start, cols.len());
+        }
+        GF2MatView::Copy(copy)
+    }
+
+    /// Whether the view borrows the data rather than copying it
+    pub fn is_borrowed(&self) -> bool {
+        matches!(self, GF2MatView::Window(_))
+    }
+}
+
+/// A mutable view into part of a matrix, see `GF2MatLikeMut::view_mut`
+pub enum GF2MatViewMut<'a, const ALIGN: usize> {
+    Window(GF2MatWindowMut<'a, ALIGN>),
+    /// A shifted copy of the view, `parent` covers the chunks it was taken
+    /// from and `col_offset` is where the view starts within them.
+    Copy {
+        copy: AlignedGF2Mat<ALIGN>,
+        parent: GF2MatWindowMut<'a, ALIGN>,
+        col_offset: usize,
+    },
+}
+
+impl<'a, const ALIGN: usize> GF2MatViewMut<'a, ALIGN> {
+    pub(crate) fn new<M, R, C>(mat: &'a mut M, rows: R, cols: C) -> Self
+    where
+        M: GF2MatLikeMut<ALIGN> + ?Sized,
+        R: RangeBounds<usize>,
+        C: RangeBounds<usize>,
+    {
+        let rows = resolve_range(rows, mat.nrows());
+        let cols = resolve_range(cols, mat.ncols());
+        let (first_chunk, nchunks) = covering_chunks::<ALIGN>(&cols);
+        let aligned = is_aligned::<ALIGN>(&cols, mat.ncols());
+        let parent =
+            unsafe { mat.get_window_mut_unchecked(rows.start, first_chunk, rows.len(), nchunks) };
+        if aligned {
+            return GF2MatViewMut::Window(parent);
+        }
+        let col_offset = cols.start - first_chunk * ALIGN * U8SZ;
+        let mut copy = AlignedGF2Mat::zero(rows.len(), cols.len());
+        for i in 0..rows.len() {
+            let src = unsafe { parent.row_slice_unchecked(i) };
+            let dst = unsafe { copy.row_slice_mut_unchecked(i) };
+            copy_bits(dst, 0, src, col_offset, cols.len());
+        }
+        GF2MatViewMut::Copy {
+            copy,
+            parent,
+            col_offset,
+        }
+    }
+
+    /// Whether the view borrows the data rather than copying it
+    pub fn is_borrowed(&self) -> bool {
+        matches!(self, GF2MatViewMut::Window(_))
+    }
+}
+
+impl<const ALIGN: usize> Drop for GF2MatViewMut<'_, ALIGN> {
+    fn drop(&mut self) {
+        if let GF2MatViewMut::Copy {
+            copy,
+            parent,
+            col_offset,
+        } = self
+        {
+            for i in 0..copy.nrows() {
+                let src = unsafe { copy.row_slice_unchecked(i) };
+                let dst = unsafe { parent.row_slice_mut_unchecked(i) };
+                copy_bits(dst, *col_offset, src, 0, copy.ncols());
+            }
+        }
+    }
+}
+
+macro_rules! delegate_mat_like {
+    ($mat:expr, $method:ident) => {
+        match $mat {
+            Self::Window(w) => w.$method(),
+            Self::Copy(c) => c.$method(),
+        }
+    };
+}
+
+macro_rules! delegate_mat_like_mut {
+    ($mat:expr, $method:ident) => {
+        match $mat {
+            Self::Window(w) => w.$method(),
+            Self::Copy { copy: c, .. } => c.$method(),
+        }
+    };
+}
+
+unsafe impl<const ALIGN: usize> GF2MatLike<ALIGN> for GF2MatView<'_, ALIGN> {
+    fn nrows(&self) -> usize {
+        delegate_mat_like!(self, nrows)
+    }
+    fn nchunk_cols(&self) -> usize {
+        delegate_mat_like!(self, nchunk_cols)
+    }
+    fn ncols(&self) -> usize {
+        delegate_mat_like!(self, ncols)
+    }
+    fn data_ptr(&self) -> *const u8 {
+        delegate_mat_like!(self, data_ptr)
+    }
+    fn row_stride(&self) -> usize {
+        delegate_mat_like!(self, row_stride)
+    }
+}
+
+unsafe impl<const ALIGN: usize> GF2MatLike<ALIGN> for GF2MatViewMut<'_, ALIGN> {
+    fn nrows(&self) -> usize {
+        delegate_mat_like_mut!(self, nrows)
+    }
+    fn nchunk_cols(&self) -> usize {
+        delegate_mat_like_mut!(self, nchunk_cols)
+    }
+    fn ncols(&self) -> usize {
+        delegate_mat_like_mut!(self, ncols)
+    }
+    fn data_ptr(&self) -> *const u8 {
+        delegate_mat_like_mut!(self, data_ptr)
+    }
+    fn row_stride(&self) -> usize {
+        delegate_mat_like_mut!(self, row_stride)
+    }
+}
+
+unsafe impl<const ALIGN: usize> GF2MatLikeMut<ALIGN> for GF2MatViewMut<'_, ALIGN> {
+    unsafe fn data_ptr_mut(&mut self) -> *mut u8 {
+        match self {
+            Self::Window(w) => unsafe { w.data_ptr_mut() },
+            Self::Copy { copy: c, .. } => unsafe { c.data_ptr_mut() },
+        }
+    }
+}